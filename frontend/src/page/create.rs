@@ -1,5 +1,5 @@
 use seed::{prelude::*, *};
-use shared::model::http::*;
+use shared::model::{http::*, BoardPiece, CreateGameForm, GameOptions, WhitePlayer};
 
 use crate::request::create::*;
 use crate::Msg::CreateGame;
@@ -11,11 +11,15 @@ pub fn init() -> Model {
 pub enum Msg {
     Submit,
     Fetched(fetch::Result<String>),
+    ToggleExpansion(BoardPiece),
+    ToggleTournament,
+    WhiteChanged(String),
 }
 
 #[derive(Default)]
 pub struct Model {
     text: Option<Status>,
+    options: GameOptions,
 }
 
 enum Status {
@@ -26,10 +30,14 @@ enum Status {
 pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
     match msg {
         Msg::Submit => match LocalStorage::get("id") {
-            Ok(id) => {
+            Ok(creator) => {
+                let form = CreateGameForm {
+                    creator,
+                    options: model.options,
+                };
                 orders
                     .skip()
-                    .perform_cmd(async move { Msg::Fetched(send_create_request(id).await) });
+                    .perform_cmd(async move { Msg::Fetched(send_create_request(form).await) });
             }
             Err(_) => {
                 model.text = Some(Status::Error("User not logged in".into()));
@@ -53,16 +61,81 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
         Msg::Fetched(Err(text)) => {
             model.text = Some(Status::Error(format!("{text:?}")));
         }
+
+        Msg::ToggleExpansion(piece) => {
+            let options = &mut model.options;
+            match piece {
+                BoardPiece::Mosquito => options.mosquito = !options.mosquito,
+                BoardPiece::Ladybug => options.ladybug = !options.ladybug,
+                BoardPiece::Pillbug => options.pillbug = !options.pillbug,
+                _ => (),
+            }
+        }
+        Msg::ToggleTournament => {
+            model.options.tournament = !model.options.tournament;
+        }
+        Msg::WhiteChanged(white) => {
+            model.options.white = match white.as_str() {
+                "Creator" => WhitePlayer::Creator,
+                "Opponent" => WhitePlayer::Opponent,
+                _ => WhitePlayer::Random,
+            };
+        }
     }
 }
 
-pub fn view<Ms: 'static>(model: &Model) -> Node<Ms> {
+fn checkbox(
+    label: &str,
+    checked: bool,
+    msg: impl Fn() -> Msg + Clone + 'static,
+) -> Node<crate::Msg> {
+    div![
+        input![
+            attrs! { At::Type => "checkbox", At::Checked => checked.as_at_value() },
+            ev(Ev::Change, move |_| CreateGame(msg())),
+        ],
+        label
+    ]
+}
+
+fn options(model: &Model) -> Node<crate::Msg> {
+    use BoardPiece::*;
+    let options = &model.options;
+
+    div![
+        C!("inputs"),
+        [Mosquito, Ladybug, Pillbug].into_iter().map(|piece| {
+            checkbox(&format!("{piece:?}"), options.enabled(piece), move || {
+                Msg::ToggleExpansion(piece)
+            })
+        }),
+        checkbox("Tournament opening", options.tournament, || {
+            Msg::ToggleTournament
+        }),
+        div![
+            "White: ",
+            select![
+                ["Random", "Creator", "Opponent"].into_iter().map(|white| {
+                    let selected = format!("{:?}", options.white) == white;
+                    option![
+                        attrs! { At::Value => white, At::Selected => selected.as_at_value() },
+                        white
+                    ]
+                }),
+                input_ev(Ev::Change, |white| CreateGame(Msg::WhiteChanged(white))),
+            ]
+        ],
+    ]
+}
+
+pub fn view(model: &Model) -> Node<crate::Msg> {
     let body = || {
         form![
             ev(Ev::Submit, |event| {
                 event.prevent_default();
                 CreateGame(Msg::Submit)
             }),
+            options(model),
             div![C!("center-button"), button![C!["button"], "Create"]],
         ]
     };
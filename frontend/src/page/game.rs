@@ -75,7 +75,6 @@ pub fn init(mut url: Url, orders: &mut impl Orders<Msg>) -> Option<Model> {
 pub enum Msg {
     FetchGame(fetch::Result<String>),
     SentMove(fetch::Result<String>),
-    CompleteGame(fetch::Result<String>),
 
     Open,
     Close,
@@ -112,11 +111,18 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
             log("CLOSE");
         }
         Msg::MessageReceived(msg) => {
-            if let Ok(r#move) = msg.json::<Move>() {
+            if let Ok(GameUpdate { r#move, outcome }) = msg.json::<GameUpdate>() {
+                // Passes are played by the server, so they are never our own move
+                if r#move.kind == MoveKind::Pass {
+                    let color = r#move.piece.color;
+                    play_move(model, r#move);
+                    model.label = Some(format!("{color:?} has no legal moves and passes"));
+                }
                 // No need to play the move if we just played it.
-                if !just_my_move(model, &r#move) {
+                else if !just_my_move(model, &r#move) {
                     let rad = sq_radius(r#move.sq);
                     play_move(model, r#move);
+                    update_outcome(model);
                     if rad > model.radius {
                         model.radius = rad;
                         model.gridv3 = create_gridv3(rad);
@@ -125,6 +131,10 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
                 } else {
                     log("IGNORE");
                 }
+                follow_analysis(model);
+
+                // Only the server can tell when a position has been repeated
+                server_outcome(model, outcome);
             }
         }
 
@@ -133,11 +143,6 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
                 model.label = Some(format!("{e:?}"));
             }
         }
-        Msg::CompleteGame(resp) => {
-            if let Err(e) = parse_resp(resp) {
-                model.label = Some(format!("{e:?}"));
-            }
-        }
 
         Msg::FetchGame(res) => match parse_resp(res) {
             Ok(resp) => {
@@ -145,13 +150,8 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
                 model.color = get_color(&game);
                 model.game = Some(game);
                 if let Some(color) = model.color {
-                    use BoardPiece::*;
-                    let items = [Ant, Beetle, Grasshopper, Spider, Queen]
-                        .into_iter()
-                        .map(|r#type| Piece { color, r#type });
-
                     let board = get_board(model).unwrap();
-                    model.menu = Some(Menu::new(items, board));
+                    model.menu = Some(Menu::new(color, board));
                 }
 
                 let rad = get_radius(model);
@@ -204,8 +204,10 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
                         .unwrap()
                         .place_piece(selected_piece.piece);
 
+                    let (piece, old_sq) = (selected_piece.piece, selected_piece.old_square);
                     let board = get_board_mut(model).unwrap();
-                    board.place_piece(selected_piece.piece, sq, Some(selected_piece.old_square));
+                    let kind = move_kind(board, &piece, old_sq, sq);
+                    board.place_piece(piece, sq, Some(old_sq));
 
                     let rad = get_radius(model);
                     if rad > model.radius {
@@ -214,20 +216,12 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
                         grid_from_board(model);
                     }
 
-                    if let Some(r#move) = get_move(
-                        model,
-                        selected_piece.piece,
-                        sq,
-                        Some(selected_piece.old_square),
-                    ) {
+                    if let Some(r#move) = get_move(model, piece, sq, Some(old_sq), kind) {
                         orders.perform_cmd(async move { Msg::SentMove(send_move(r#move).await) });
                     }
 
-                    if game_complete(model) {
-                        let id = model.game.as_ref().unwrap()._id;
-                        orders
-                            .perform_cmd(async move { Msg::CompleteGame(complete_game(id).await) });
-                    }
+                    update_outcome(model);
+                    follow_analysis(model);
                 } else {
                     place_piece_back(model, selected_piece);
                 }
@@ -243,19 +237,16 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
             let (x, y) = get_mouse_pos(model, mm);
 
             let my_turn = legal_turn(model);
-            let correct_piece = legal_piece(model);
 
             if let Some(sel) = model.piece.as_mut() {
                 sel.x = x;
                 sel.y = y;
 
-                if my_turn && correct_piece {
-                    let piece = &sel.piece;
+                if my_turn {
                     let board = &mut model.game.as_mut().unwrap().board;
 
                     if model.legal_moves_cache.is_none() {
-                        model.legal_moves_cache =
-                            Some(legal_moves(piece, board, Some(sel.old_square)));
+                        model.legal_moves_cache = Some(drag_moves(board, sel));
                     }
                 }
             }
@@ -272,7 +263,8 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
             if legal_move(model, sq) {
                 let r#type: BoardPiece = id.into();
                 let color = model.color.unwrap();
-                let piece = Piece { r#type, color };
+                let board = get_board(model).unwrap();
+                let piece = board.identify(Piece::new(r#type, color), None);
                 place_piece(model, piece, sq);
 
                 model.menu.as_mut().unwrap().reduce_piece(r#type);
@@ -281,7 +273,7 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
                     b.place_piece(piece, sq, None);
                 }
 
-                if let Some(r#move) = get_move(model, piece, sq, None) {
+                if let Some(r#move) = get_move(model, piece, sq, None, MoveKind::Regular) {
                     orders.perform_cmd(async move { Msg::SentMove(send_move(r#move).await) });
                 }
                 let rad = get_radius(model);
@@ -291,10 +283,8 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
                     grid_from_board(model);
                 }
 
-                if game_complete(model) {
-                    let id = model.game.as_ref().unwrap()._id;
-                    orders.perform_cmd(async move { Msg::CompleteGame(complete_game(id).await) });
-                }
+                update_outcome(model);
+                follow_analysis(model);
             }
 
             model.legal_moves_cache = None;
@@ -306,7 +296,7 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
             if legal_turn(model) {
                 let board = &mut model.game.as_mut().unwrap().board;
                 if model.legal_moves_cache.is_none() {
-                    model.legal_moves_cache = Some(legal_moves(&piece, board, None));
+                    model.legal_moves_cache = Some(placements(board, &piece));
                 }
                 set_highlight(model, true);
             }
@@ -340,6 +330,9 @@ pub fn view(model: &Model) -> Node<crate::Msg> {
         IF!(model.menu.is_some() => {
             div![C!("piece-menu"), model.menu.as_ref().unwrap().to_node()]
         }),
+        IF!(outcome(model).is_some() => {
+            h2![C!("success"), outcome_text(outcome(model).unwrap())]
+        }),
         IF!(model.label.is_some() => match model.label {
             Some(ref s) => h2! [C!("error"), s],
             _ => unreachable!()
@@ -115,18 +115,37 @@ impl Hex {
 
         let c = if self.highlight { "selected-piece" } else { "" };
 
-        r#use![attrs! {
-            At::Href => "#pod",
-            At::Transform => format!("translate({x}, {y})"),
-            At::Fill => fill,
-            At::Stroke => "gold",
-            At::Opacity => opacity,
-            At::Class => c,
-            At::DropZone => "move",
-        },]
+        g![
+            r#use![attrs! {
+                At::Href => "#pod",
+                At::Transform => format!("translate({x}, {y})"),
+                At::Fill => fill,
+                At::Stroke => "gold",
+                At::Opacity => opacity,
+                At::Class => c,
+                At::DropZone => "move",
+            },],
+            self.top().map(|piece| piece_label(piece, x, y)),
+        ]
     }
 }
 
+/// The name of the piece, e.g. `wA1`, drawn on top of its hexagon
+pub fn piece_label(piece: &Piece, x: f32, y: f32) -> Node<crate::Msg> {
+    text![
+        attrs! {
+            At::X => x,
+            At::Y => y,
+            At::FontSize => "2.5",
+            At::TextAnchor => "middle",
+            At::DominantBaseline => "central",
+            At::Fill => "white",
+        },
+        style! { St::PointerEvents => "none" },
+        piece.to_string(),
+    ]
+}
+
 pub fn create_gridv3(r: usize) -> Vec<Hex> {
     use std::cmp::{max, min};
     let r = r as isize;
@@ -8,29 +8,20 @@ pub struct Menu {
 }
 
 impl Menu {
-    pub fn new<I: Iterator<Item = Piece>>(pieces: I, board: &Board) -> Self {
-        let items = pieces
-            .map(|piece| MenuEntry {
-                count_left: piece_to_count(piece.r#type),
-                piece,
+    pub fn new(color: Color, board: &Board) -> Self {
+        let items = board
+            .options
+            .pieces()
+            .map(|r#type| {
+                let piece = Piece::new(r#type, color);
+                MenuEntry {
+                    count_left: board.in_reserve(&piece),
+                    piece,
+                }
             })
             .collect();
 
-        let mut menu = Menu { items };
-        let color = menu.items.first().unwrap().piece.color;
-
-        for square in board.values() {
-            for bp in square
-                .pieces
-                .iter()
-                .filter_map(|piece| (piece.color == color).then(|| piece.r#type))
-            {
-                menu.reduce_piece(bp);
-            }
-        }
-
-
-        menu
+        Menu { items }
     }
     pub fn to_node(&self) -> Node<crate::Msg> {
         div![
@@ -52,17 +43,6 @@ impl Menu {
     }
 }
 
-#[inline]
-fn piece_to_count(bp: BoardPiece) -> usize {
-    match bp {
-        BoardPiece::Ant => 3,
-        BoardPiece::Grasshopper => 3,
-        BoardPiece::Spider => 2,
-        BoardPiece::Beetle => 2,
-        BoardPiece::Queen => 1,
-    }
-}
-
 // Maybe rename stuff later
 pub struct MenuEntry {
     count_left: usize,
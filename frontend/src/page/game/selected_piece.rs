@@ -14,13 +14,16 @@ impl SelectedPiece {
 
         let fill = piece_color(self.piece.r#type, self.piece.color);
 
-        r#use![attrs! {
-            At::Href => "#pod",
-            At::Transform => format!("translate({x}, {y})"),
-            At::Fill => fill,
-            At::Stroke => "gold",
-            At::Opacity => "1.0",
-        },]
+        g![
+            r#use![attrs! {
+                At::Href => "#pod",
+                At::Transform => format!("translate({x}, {y})"),
+                At::Fill => fill,
+                At::Stroke => "gold",
+                At::Opacity => "1.0",
+            },],
+            piece_label(&self.piece, x, y),
+        ]
     }
 }
 
@@ -1,7 +1,7 @@
 use super::*;
 use crate::page::game::*;
 use seed::{self, prelude::*};
-use shared::{model::*, ObjectId};
+use shared::{model::*, r#move::*, ObjectId};
 use web_sys::MouseEvent;
 
 pub fn get_board_mut(model: &mut Model) -> Option<&mut Board> {
@@ -22,13 +22,21 @@ pub fn place_piece(model: &mut Model, piece: Piece, sq: Square) {
 }
 
 pub fn play_move(model: &mut Model, r#move: Move) {
-    if let Some(old) = r#move.old_sq {
+    let (piece, sq, old_sq, kind) = (r#move.piece, r#move.sq, r#move.old_sq, r#move.kind);
+    if let Err(e) = get_board_mut(model).unwrap().play_move(r#move) {
+        model.label = Some(format!("{e:?}"));
+        return;
+    }
+    if kind == MoveKind::Pass {
+        return;
+    }
+
+    if let Some(old) = old_sq {
         if let Some(hex) = get_hex_from_square(model, old) {
             hex.remove_top();
         }
     }
-    place_piece(model, r#move.piece, r#move.sq);
-    get_board_mut(model).unwrap().play_move(r#move);
+    place_piece(model, piece, sq);
 }
 
 pub fn get_mouse_pos(model: &Model, mm: &MouseEvent) -> (f32, f32) {
@@ -47,6 +55,9 @@ pub fn piece_color(b: BoardPiece, color: Color) -> &'static str {
             BoardPiece::Spider => "peru",
             BoardPiece::Grasshopper => "palegreen",
             BoardPiece::Beetle => "rebeccapurple",
+            BoardPiece::Mosquito => "silver",
+            BoardPiece::Ladybug => "tomato",
+            BoardPiece::Pillbug => "teal",
         }
     } else {
         match b {
@@ -55,6 +66,9 @@ pub fn piece_color(b: BoardPiece, color: Color) -> &'static str {
             BoardPiece::Spider => "brown",
             BoardPiece::Grasshopper => "green",
             BoardPiece::Beetle => "indigo",
+            BoardPiece::Mosquito => "dimgray",
+            BoardPiece::Ladybug => "darkred",
+            BoardPiece::Pillbug => "darkslategray",
         }
     }
 }
@@ -112,7 +126,13 @@ pub fn set_highlight(model: &mut Model, val: bool) {
     }
 }
 
-pub fn get_move(model: &Model, sel: Piece, sq: Square, old_sq: Option<Square>) -> Option<Move> {
+pub fn get_move(
+    model: &Model,
+    sel: Piece,
+    sq: Square,
+    old_sq: Option<Square>,
+    kind: MoveKind,
+) -> Option<Move> {
     let id: Result<ObjectId, _> = LocalStorage::get("id");
     let name: Result<String, _> = LocalStorage::get("name");
 
@@ -124,24 +144,36 @@ pub fn get_move(model: &Model, sel: Piece, sq: Square, old_sq: Option<Square>) -
                 game_id: game._id,
                 old_sq,
                 sq,
+                kind,
             });
         }
     }
     None
 }
 
+/// The first player plays white, the second plays black. In an analysis game
+/// the player always plays the side to move
 pub fn get_color(game: &GameResource) -> Option<Color> {
     LocalStorage::get("name").ok().and_then(|name: String| {
         game.players
             .iter()
             .position(|n| n == &name)
             .map(|i| match i {
+                _ if game.analysis => game.board.to_move(),
                 0 => Color::White,
                 _ => Color::Black,
             })
     })
 }
 
+/// Hands an analysis game over to the side to move, with its pieces in the menu
+pub fn follow_analysis(model: &mut Model) {
+    if let Some(game) = model.game.as_ref().filter(|game| game.analysis) {
+        model.color = get_color(game);
+        model.menu = model.color.map(|color| Menu::new(color, &game.board));
+    }
+}
+
 pub fn grid_from_board(model: &mut Model) {
     for (&sq, board_square) in model.game.as_ref().unwrap().board.iter() {
         for piece in &board_square.pieces {
@@ -152,40 +184,92 @@ pub fn grid_from_board(model: &mut Model) {
 }
 
 pub fn get_move_color(model: &Model) -> Option<Color> {
-    model.game.as_ref().map(|game| match game.board.turns % 2 {
-        0 => Color::White,
-        _ => Color::Black,
-    })
+    model.game.as_ref().map(|game| game.board.to_move())
 }
 
 pub fn legal_turn(model: &Model) -> bool {
+    if outcome(model).is_some() {
+        return false;
+    }
+
     match (get_move_color(model), model.color) {
         (Some(c1), Some(c2)) => c1 == c2,
         _ => false,
     }
 }
 
-pub fn legal_piece(model: &Model) -> bool {
-    match (model.piece.as_ref(), model.color) {
-        (Some(sel), Some(ref color)) => sel.piece.color == *color,
-        _ => false,
+/// Squares the dragged piece can go to, either by itself or thrown by one of
+/// our pillbugs
+pub fn drag_moves(board: &mut Board, sel: &SelectedPiece) -> Vec<Square> {
+    let color = board.to_move();
+    let moves = generate_moves(board, color);
+    squares(moves.iter().filter(|m| m.old_sq == Some(sel.old_square)))
+}
+
+/// Squares a piece of the kind of `piece` can be placed on from the reserve
+pub fn placements(board: &mut Board, piece: &Piece) -> Vec<Square> {
+    let moves = generate_moves(board, piece.color);
+    let placed = |m: &&Move| m.old_sq.is_none() && m.piece.r#type == piece.r#type;
+    squares(moves.iter().filter(placed))
+}
+
+fn squares<'a>(moves: impl Iterator<Item = &'a Move>) -> Vec<Square> {
+    let mut squares = Vec::new();
+    for r#move in moves {
+        if !squares.contains(&r#move.sq) {
+            squares.push(r#move.sq);
+        }
     }
+    squares
 }
 
-pub fn game_complete(model: &Model) -> bool {
-    model
-        .game
-        .as_ref()
-        .map(|game| game.board.is_complete())
-        .unwrap_or(false)
+pub fn update_outcome(model: &mut Model) {
+    if let Some(game) = model.game.as_mut() {
+        game.outcome = game.board.outcome().or(game.outcome);
+    }
+}
+
+/// Ends the game if the server says it is over, also when the board does not
+/// show it
+pub fn server_outcome(model: &mut Model, outcome: Option<GameOutcome>) {
+    if let (Some(game), Some(_)) = (model.game.as_mut(), outcome) {
+        game.outcome = outcome;
+    }
+}
+
+pub fn outcome(model: &Model) -> Option<GameOutcome> {
+    model.game.as_ref().and_then(|game| game.outcome)
+}
+
+pub fn outcome_text(outcome: GameOutcome) -> String {
+    let result = match outcome.winner() {
+        Some(color) => format!("{color:?} wins"),
+        None => "Draw".into(),
+    };
+
+    let reason = match (outcome.reason(), outcome.winner()) {
+        (OutcomeReason::QueenSurrounded, Some(Color::White)) => "the black queen is surrounded",
+        (OutcomeReason::QueenSurrounded, _) => "the white queen is surrounded",
+        (OutcomeReason::BothQueensSurrounded, _) => "both queens are surrounded",
+        (OutcomeReason::Repetition, _) => "the same position came up three times",
+        (OutcomeReason::NoLegalMoves, _) => "neither player can move",
+    };
+
+    format!("{result}, {reason}!")
 }
 
 pub fn just_my_move(model: &Model, r#move: &Move) -> bool {
-    model
-        .color
-        .as_ref()
-        .map(|color| *color == r#move.piece.color)
-        .unwrap_or(false)
+    // A pillbug throw is made by the owner of the pillbug, which stays put
+    let mover = match r#move.kind {
+        MoveKind::Pillbug(sq) => get_board(model)
+            .and_then(|board| board.get(&sq))
+            .map(|bs| bs.top().color),
+        _ => Some(r#move.piece.color),
+    };
+
+    // Nobody else plays in an analysis game
+    let analysis = model.game.as_ref().map_or(false, |game| game.analysis);
+    model.color.is_some() && (analysis || model.color == mover)
 }
 
 pub fn sq_radius(sq: Square) -> usize {
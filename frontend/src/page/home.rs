@@ -1,7 +1,7 @@
 use seed::{prelude::*, *};
 use shared::model::{
-    CreateGameChallenge, CreateGameChallengeBundle, CreateGameFormResponse, OnGoingGame,
-    ResponseBody,
+    BoardPiece, CreateGameChallenge, CreateGameChallengeBundle, CreateGameFormResponse,
+    GameOptions, OnGoingGame, ResponseBody,
 };
 use shared::ObjectId;
 
@@ -44,10 +44,11 @@ fn challenge_from_bundle(bundle: Vec<CreateGameChallengeBundle>) -> Vec<CreateGa
                 creator_id,
             } = user;
 
-            games.into_iter().map(move |game_id| CreateGameChallenge {
+            games.into_iter().map(move |game| CreateGameChallenge {
                 name: name.clone(),
-                _id: game_id,
+                _id: game._id,
                 creator: creator_id,
+                options: game.options,
             })
         })
         .collect()
@@ -161,16 +162,36 @@ fn challenge<Ms: 'static>(game: &CreateGameChallenge) -> Node<Ms> {
     ]
 }
 
+fn variant(options: &GameOptions) -> String {
+    let mut pieces = options
+        .pieces()
+        .filter(BoardPiece::is_expansion)
+        .map(|piece| format!("{piece:?}"))
+        .collect::<Vec<_>>();
+
+    if options.tournament {
+        pieces.push("Tournament".into());
+    }
+
+    match pieces.is_empty() {
+        true => "Base".into(),
+        false => pieces.join(", "),
+    }
+}
+
 fn available_games<Ms: 'static>(model: &Model) -> Node<Ms> {
     div![
         h1!["Available Games!"],
         table![
             C!("challenge-table"),
-            tr![th!["Challenger"], th!["Accept"],],
-            model
-                .available_games
-                .iter()
-                .map(|game| { tr![td![&game.name], td![challenge(game)]] })
+            tr![th!["Challenger"], th!["Variant"], th!["Accept"],],
+            model.available_games.iter().map(|game| {
+                tr![
+                    td![&game.name],
+                    td![variant(&game.options)],
+                    td![challenge(game)]
+                ]
+            })
         ]
     ]
 }
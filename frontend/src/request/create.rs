@@ -1,12 +1,12 @@
 use super::url;
 
 use seed::prelude::*;
-use shared::ObjectId;
+use shared::model::CreateGameForm;
 
-pub async fn send_create_request(creator: ObjectId) -> fetch::Result<String> {
+pub async fn send_create_request(form: CreateGameForm) -> fetch::Result<String> {
     Request::new(url("create-game"))
         .method(Method::Post)
-        .json(&creator)?
+        .json(&form)?
         .fetch()
         .await?
         .check_status()?
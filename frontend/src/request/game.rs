@@ -23,14 +23,3 @@ pub async fn send_move(r#move: Move) -> fetch::Result<String> {
         .text()
         .await
 }
-
-pub async fn complete_game(id: ObjectId) -> fetch::Result<String> {
-    Request::new(url("game"))
-        .method(Method::Delete)
-        .json(&id)?
-        .fetch()
-        .await?
-        .check_status()?
-        .text()
-        .await
-}
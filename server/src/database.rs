@@ -1,12 +1,15 @@
 use const_format::formatcp;
 use futures::stream::StreamExt;
 use mongodb::{
-    bson::{self, doc, oid::ObjectId},
+    bson::{self, doc, oid::ObjectId, Document},
     error::Error,
     options::{ClientOptions, Credential},
     Client, Database,
 };
-use shared::model::*;
+use shared::{
+    game_string::{format_game, parse_game, GameStringError},
+    model::*,
+};
 
 use crate::model::{game::Game, user::User};
 
@@ -31,10 +34,19 @@ pub enum DatabaseError
 {
     UserAlreadyExist,
     UserDontExist,
+    #[allow(dead_code)]
     DbError(mongodb::error::Error),
     NoDocumentFound,
     TooManyGames,
-    GameNotComplete,
+    NotYourTurn,
+    NotYourPiece,
+    IllegalMove(MoveError),
+    InvalidGameString(GameStringError),
+    /// The recorded moves do not lead to the stored board, e.g. because the
+    /// game was played before moves were recorded
+    CorruptHistory,
+    /// Another move was stored while this one was being played
+    Conflict,
 }
 
 
@@ -56,6 +68,51 @@ pub async fn connect() -> Result<Client, Error>
     Client::with_options(client_options)
 }
 
+/// Brings documents stored by earlier versions up to date. It runs at
+/// startup and does nothing once they are
+pub async fn migrate(db: Database) -> Result<(), Error>
+{
+    // Games were marked `complete` before they had an outcome. Only games
+    // with a decided board were, so the board tells the outcome
+    #[derive(serde::Deserialize)]
+    struct LegacyGame
+    {
+        _id:   ObjectId,
+        board: Board,
+    }
+
+    let games = db.collection::<LegacyGame>(GAMES);
+    let mut legacy = games.find(doc! { "complete": { "$exists": true } }, None).await?;
+    while let Some(game) = legacy.next().await
+    {
+        let game = game?;
+        let outcome = bson::to_bson(&game.board.outcome()).unwrap();
+        let update = doc! { "$set": { "outcome": outcome }, "$unset": { "complete": "" } };
+        games.update_one(doc! { "_id": game._id }, update, None).await?;
+    }
+
+    // Open challenges were stored as bare ids before they had options, which
+    // were the default ones
+    let options = bson::to_bson(&GameOptions::default()).unwrap();
+    let challenge = doc! {
+        "$cond": [
+            { "$eq": [{ "$type": "$$challenge" }, "objectId"] },
+            { "_id": "$$challenge", "options": { "$literal": options } },
+            "$$challenge"
+        ]
+    };
+    let update = doc! {
+        "$set": {
+            "create_games": { "$map": { "input": "$create_games", "as": "challenge", "in": challenge } }
+        }
+    };
+    db.collection::<Document>(USERS)
+        .update_many(doc! { "create_games": { "$type": "objectId" } }, vec![update], None)
+        .await?;
+
+    Ok(())
+}
+
 pub async fn login(db: Database, cred: UserCredentials) -> DatabaseResult<ObjectId>
 {
     let col = db.collection::<User>(USERS);
@@ -119,7 +176,11 @@ async fn count_create_game(db: Database, id: &ObjectId) -> DatabaseResult<u32>
     }
 }
 
-pub async fn create_game(db: Database, user_id: ObjectId) -> DatabaseResult<()>
+pub async fn create_game(
+    db: Database,
+    user_id: ObjectId,
+    options: GameOptions,
+) -> DatabaseResult<()>
 {
     let col = db.collection::<User>(USERS);
 
@@ -128,8 +189,13 @@ pub async fn create_game(db: Database, user_id: ObjectId) -> DatabaseResult<()>
         return Err(DatabaseError::TooManyGames);
     }
 
+    let challenge = GameChallenge {
+        _id: ObjectId::new(),
+        options,
+    };
+
     let user = doc! { "_id": &user_id };
-    let update = doc! { "$push": { "create_games": ObjectId::new() } };
+    let update = doc! { "$push": { "create_games": bson::to_bson(&challenge).unwrap() } };
 
     match col.update_one(user, update, None).await
     {
@@ -146,11 +212,29 @@ async fn remove_user_create_game(
 {
     let col = db.collection::<User>(USERS);
 
-    col.update_one(doc! { "_id": creator }, doc! { "$pull": { "create_games": game } }, None)
-        .await?;
+    col.update_one(
+        doc! { "_id": creator },
+        doc! { "$pull": { "create_games": { "_id": game } } },
+        None,
+    )
+    .await?;
     Ok(())
 }
 
+async fn find_user_create_game(
+    db: Database,
+    creator: &ObjectId,
+    game: &ObjectId,
+) -> DatabaseResult<GameChallenge>
+{
+    find_user_by_id(db, *creator)
+        .await?
+        .create_games
+        .into_iter()
+        .find(|challenge| &challenge._id == game)
+        .ok_or(DatabaseError::NoDocumentFound)
+}
+
 async fn add_game_id_to_users(
     db: Database,
     id: &ObjectId,
@@ -178,20 +262,29 @@ pub async fn accept_game(db: Database, form: CreateGameFormResponse) -> Database
         creator,
     } = form;
 
+    let options = find_user_create_game(db.clone(), &creator, &game).await?.options;
     remove_user_create_game(db.clone(), &creator, &game).await?;
 
     let games = db.collection::<Game>(GAMES);
 
+    let creator_white = match options.white
+    {
+        WhitePlayer::Creator => true,
+        WhitePlayer::Opponent => false,
+        WhitePlayer::Random =>
+        {
+            // @NOTE: Take a look at this in the future haha Xdd
+            use getrandom::getrandom;
+            let mut byte = [0_u8];
+            getrandom(&mut byte).expect("random");
+            byte[0] >= 128
+        },
+    };
 
-    // @NOTE: Take a look at this in the future haha Xdd
-    use getrandom::getrandom;
-    let mut byte = [0_u8];
-    getrandom(&mut byte).expect("random");
-
-    let players = if byte[0] >= 128 { [creator, user] } else { [user, creator] };
+    let players = if creator_white { [creator, user] } else { [user, creator] };
 
     let id = games
-        .insert_one(Game::new(players), None)
+        .insert_one(Game::new(players, options), None)
         .await?
         .inserted_id
         .as_object_id()
@@ -228,6 +321,24 @@ pub async fn home(db: Database, id: ObjectId) -> DatabaseResult<Vec<CreateGameCh
         .await)
 }
 
+/// The names of the players of a game, in the same order as their ids in
+/// `players`, after a `$lookup` of them into `users`
+fn player_names() -> Document
+{
+    doc! {
+        "$map": {
+            "input": "$players",
+            "as": "player",
+            "in": {
+                "$arrayElemAt": [
+                    "$users.name",
+                    { "$indexOfArray": ["$users._id", "$$player"] }
+                ]
+            }
+        }
+    }
+}
+
 //TODO: Whats the point of the uuid? just use the _id
 pub async fn get_active_games(db: Database) -> DatabaseResult<Vec<OnGoingGame>>
 {
@@ -243,7 +354,7 @@ pub async fn get_active_games(db: Database) -> DatabaseResult<Vec<OnGoingGame>>
      * as: (evt) rename the thing
      *
      * However, the lookup gives us the whole users object, aka
-     * "players": [
+     * "users": [
      * {"_id": "...",
      * "name, "Sivert"},
      * {"_id": "...",
@@ -252,16 +363,17 @@ pub async fn get_active_games(db: Database) -> DatabaseResult<Vec<OnGoingGame>>
      * And we _only_ want the names.
      *
      * Therefore, we do a project where we `snatch` _only_ the player name out of
-     * the object
+     * the object. The lookup finds every user once and in no particular order,
+     * so the names are picked by id to keep white first, see `player_names`
      *
      */
 
-    Ok(col
+    let mut games = col
         .aggregate(
             [
                 doc! {
                     "$match": {
-                        "complete": false
+                        "outcome": null
                     }
                 },
                 doc! {
@@ -269,93 +381,151 @@ pub async fn get_active_games(db: Database) -> DatabaseResult<Vec<OnGoingGame>>
                         "from": USERS,
                         "localField": "players",
                         "foreignField": "_id",
-                        "as": "players"
+                        "as": "users"
                     }
                 },
                 doc! {
                     "$project": {
-                        "players": "$players.name",
+                        "players": player_names(),
                         "game_object_id": "$_id",
                     }
                 },
             ],
             None,
         )
-        .await?
-        .map(|doc| {
-            let doc = doc.unwrap();
-            let g: OnGoingGame = bson::from_document(doc).unwrap();
-            g
-        })
-        .collect()
-        .await)
+        .await?;
+
+    let mut res = Vec::new();
+    while let Some(doc) = games.next().await
+    {
+        res.push(bson::from_document(doc?)?);
+    }
+    Ok(res)
 }
 
 
 pub async fn get_game_by_id(db: Database, id: ObjectId) -> DatabaseResult<GameResource>
 {
     let col = db.collection::<Game>(GAMES);
-    col.aggregate(
-        [
-            doc! {
-                "$match": { "_id": &id }
-            },
-            doc! {
-                "$lookup": {
-                    "from": USERS,
-                    "localField": "players",
-                    "foreignField": "_id",
-                    "as": "players"
-                }
-            },
-            doc! {
-                "$project": {
-                    "players": "$players.name",
-                    "board": "$board",
-                    "complete": "$complete",
-                }
-            },
-        ],
-        None,
-    )
-    .await?
-    .map(|doc| {
-        let doc = doc.unwrap();
-        let g: GameResource = bson::from_document(doc).unwrap();
-        g
-    })
-    .next()
-    .await
-    .ok_or(DatabaseError::NoDocumentFound)
+    let mut games = col
+        .aggregate(
+            [
+                doc! {
+                    "$match": { "_id": &id }
+                },
+                doc! {
+                    "$lookup": {
+                        "from": USERS,
+                        "localField": "players",
+                        "foreignField": "_id",
+                        "as": "users"
+                    }
+                },
+                doc! {
+                    "$project": {
+                        "players": player_names(),
+                        "board": "$board",
+                        "outcome": "$outcome",
+                        "moves": "$moves",
+                        "analysis": "$analysis",
+                    }
+                },
+            ],
+            None,
+        )
+        .await?;
+
+    match games.next().await
+    {
+        Some(doc) => Ok(bson::from_document(doc?)?),
+        None => Err(DatabaseError::NoDocumentFound),
+    }
 }
 
-pub async fn play_move(db: Database, r#move: Move) -> DatabaseResult<()>
+/// Plays `move` and returns the moves that were played, each with the outcome
+/// of the game after it: the move itself, with the piece fully identified,
+/// followed by a forced pass if the opponent has no legal moves left
+pub async fn play_move(db: Database, r#move: Move) -> DatabaseResult<Vec<GameUpdate>>
 {
     let col = db.collection::<Game>(GAMES);
 
-    let query = doc! {
+    let mut query = doc! {
     "_id": r#move.game_id,
     "players": { "$in": [&r#move.player_id] }};
 
     let mut game =
         col.find_one(query.clone(), None).await?.ok_or(DatabaseError::NoDocumentFound)?;
-    game.board.play_move(r#move);
+    let turns = game.board.turns;
+
+    let color = game.color(&r#move.player_id).ok_or(DatabaseError::NoDocumentFound)?;
+    if color != game.board.to_move()
+    {
+        return Err(DatabaseError::NotYourTurn);
+    }
+    if game.board.mover(&r#move) != Some(color)
+    {
+        return Err(DatabaseError::NotYourPiece);
+    }
+
+    let game_id = r#move.game_id;
+    let r#move = game.play(r#move)?;
+    let mut played = vec![GameUpdate {
+        r#move,
+        outcome: game.outcome,
+    }];
+    if let Some(pass) = game.forced_pass(game_id)
+    {
+        played.push(GameUpdate {
+            r#move:  pass,
+            outcome: game.outcome,
+        });
+    }
 
-    col.replace_one(query, game, None).await.map(|_| ()).map_err(|e| e.into())
+    // The move was checked against the game as it was read, so it is only
+    // stored if no other move has been since
+    query.insert("board.turns", turns as i64);
+    match col.replace_one(query, game, None).await?.matched_count
+    {
+        0 => Err(DatabaseError::Conflict),
+        _ => Ok(played),
+    }
 }
 
-pub async fn complete_game(db: Database, game_id: ObjectId) -> DatabaseResult<()>
+/// The game written as a GameString, e.g. `Base;InProgress;Black[1];wA1`
+pub async fn get_game_string(db: Database, id: ObjectId) -> DatabaseResult<String>
 {
     let col = db.collection::<Game>(GAMES);
-    let filter = doc! { "_id": game_id };
-    let update = doc! { "$set": { "complete": true } };
 
-    col.find_one_and_update(filter, update, None)
-        .await
-        .map(|_| ())
-        .map_err(|e| e.into())
+    let game = col
+        .find_one(doc! { "_id": id }, None)
+        .await?
+        .ok_or(DatabaseError::NoDocumentFound)?;
+
+    if !game.history_matches()
+    {
+        return Err(DatabaseError::CorruptHistory);
+    }
+
+    format_game(&game.board.options, &game.history(), game.outcome)
+        .map_err(DatabaseError::InvalidGameString)
 }
 
+/// Replays a GameString into a new analysis game for `form.user`
+pub async fn import_game(db: Database, form: ImportGame) -> DatabaseResult<ObjectId>
+{
+    let (board, moves) = parse_game(&form.game).map_err(DatabaseError::InvalidGameString)?;
+
+    let col = db.collection::<Game>(GAMES);
+    let id = col
+        .insert_one(Game::analysis(form.user, board.options, moves), None)
+        .await?
+        .inserted_id
+        .as_object_id()
+        .unwrap();
+
+    add_game_id_to_users(db, &id, &form.user, &form.user).await?;
+    Ok(id)
+}
 
 pub fn hash(word: &str) -> String
 {
@@ -374,6 +544,22 @@ impl From<mongodb::error::Error> for DatabaseError
     }
 }
 
+impl From<bson::de::Error> for DatabaseError
+{
+    fn from(error: bson::de::Error) -> Self
+    {
+        DatabaseError::DbError(error.into())
+    }
+}
+
+impl From<MoveError> for DatabaseError
+{
+    fn from(error: MoveError) -> Self
+    {
+        DatabaseError::IllegalMove(error)
+    }
+}
+
 #[cfg(test)]
 mod test
 {
@@ -436,20 +622,24 @@ mod test
         let u1 = reg(guard, "sivert".into()).await?;
         let u2 = reg(guard, "sofie".into()).await?;
 
-        create_game(guard.db(), u1.clone()).await?;
+        create_game(guard.db(), u1, GameOptions::default()).await?;
 
-        let games = home(guard.db(), u2.clone()).await?;
-        let game = games[0].games[0].clone();
+        let games = home(guard.db(), u2).await?;
+        let game = games[0].games[0]._id;
 
         let form = CreateGameFormResponse {
-            creator: u1.clone(),
-            user: u2.clone(),
+            creator: u1,
+            user: u2,
             game,
         };
 
         let game = accept_game(guard.db(), form).await?.object_id;
 
-        Ok((u1, u2, game))
+        // Players are assigned colors at random, return them as (white, black)
+        let col = guard.db().collection::<Game>(GAMES);
+        let players = col.find_one(doc! { "_id": game }, None).await?.unwrap().players;
+
+        Ok((players[0], players[1], game))
     }
 
 
@@ -528,7 +718,7 @@ mod test
         let guard = get_guard().await?;
         let creator = reg(&guard, "sivert".into()).await?;
 
-        assert!(create_game(guard.db(), creator).await.is_ok());
+        assert!(create_game(guard.db(), creator, GameOptions::default()).await.is_ok());
         Ok(())
     }
 
@@ -538,8 +728,8 @@ mod test
         let guard = get_guard().await?;
         let creator = reg(&guard, "sivert".into()).await?;
 
-        assert!(create_game(guard.db(), creator.clone()).await.is_ok());
-        assert!(create_game(guard.db(), creator.clone()).await.is_ok());
+        assert!(create_game(guard.db(), creator, GameOptions::default()).await.is_ok());
+        assert!(create_game(guard.db(), creator, GameOptions::default()).await.is_ok());
 
         assert_eq!(home(guard.db(), creator).await?.len(), 0);
 
@@ -558,7 +748,7 @@ mod test
 
         // Create user#1 and create one game
         let id = reg(&guard, "sofie".into()).await?;
-        assert!(create_game(guard.db(), id.clone()).await.is_ok());
+        assert!(create_game(guard.db(), id, GameOptions::default()).await.is_ok());
 
 
         // Create user#2 and `n` games
@@ -567,7 +757,7 @@ mod test
 
         for _ in 0..n
         {
-            assert!(create_game(guard.db(), id.clone()).await.is_ok());
+            assert!(create_game(guard.db(), id, GameOptions::default()).await.is_ok());
         }
 
         assert_eq!(count_create_game(guard.db(), &id).await?, n);
@@ -581,16 +771,16 @@ mod test
         let guard = get_guard().await?;
         let creator = reg(&guard, "sivert".into()).await?;
 
-        assert!(create_game(guard.db(), creator.clone()).await.is_ok());
+        assert!(create_game(guard.db(), creator, GameOptions::default()).await.is_ok());
 
         let uuid = reg(&guard, "sofie".into()).await?;
         let games = home(guard.db(), uuid).await?;
-        let game = games[0].games[0].clone();
+        let game = games[0].games[0]._id;
 
-        assert_eq!(find_user_by_id(guard.db(), creator.clone()).await?.create_games.len(), 1);
+        assert_eq!(find_user_by_id(guard.db(), creator).await?.create_games.len(), 1);
         assert!(remove_user_create_game(guard.db(), &creator, &game).await.is_ok());
 
-        assert_eq!(find_user_by_id(guard.db(), creator.clone()).await?.create_games.len(), 0);
+        assert_eq!(find_user_by_id(guard.db(), creator).await?.create_games.len(), 0);
 
 
         Ok(())
@@ -603,15 +793,15 @@ mod test
         let guard = get_guard().await?;
 
         let creator = reg(&guard, "sivert".into()).await?;
-        assert!(create_game(guard.db(), creator.clone()).await.is_ok());
+        assert!(create_game(guard.db(), creator, GameOptions::default()).await.is_ok());
 
         let id = reg(&guard, "sofie".into()).await?;
-        let games = home(guard.db(), id.clone()).await?;
-        let game = games[0].games[0].clone();
+        let games = home(guard.db(), id).await?;
+        let game = games[0].games[0]._id;
 
         let form = CreateGameFormResponse {
-            creator: creator.clone(),
-            user: id.clone(),
+            creator,
+            user: id,
             game,
         };
 
@@ -627,21 +817,54 @@ mod test
         Ok(())
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_accepted_game_uses_challenge_options() -> Result<(), DatabaseError>
+    {
+        let guard = get_guard().await?;
+
+        let options = GameOptions {
+            pillbug: true,
+            white: WhitePlayer::Creator,
+            ..Default::default()
+        };
+
+        let creator = reg(&guard, "sivert".into()).await?;
+        assert!(create_game(guard.db(), creator, options).await.is_ok());
+
+        let id = reg(&guard, "sofie".into()).await?;
+        let games = home(guard.db(), id).await?;
+        assert_eq!(games[0].games[0].options, options);
+
+        let form = CreateGameFormResponse {
+            creator,
+            user: id,
+            game: games[0].games[0]._id,
+        };
+        let game = accept_game(guard.db(), form).await?.object_id;
+
+        let col = guard.db().collection::<Game>(GAMES);
+        let game = col.find_one(doc! { "_id": game }, None).await?.unwrap();
+        assert_eq!(game.players, [creator, id]);
+        assert_eq!(game.board.options, options);
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_can_get_active_games() -> Result<(), DatabaseError>
     {
         let guard = get_guard().await?;
 
         let creator = reg(&guard, "sivert".into()).await?;
-        assert!(create_game(guard.db(), creator.clone()).await.is_ok());
+        assert!(create_game(guard.db(), creator, GameOptions::default()).await.is_ok());
 
         let uuid = reg(&guard, "sofie".into()).await?;
-        let games = home(guard.db(), uuid.clone()).await?;
-        let game = games[0].games[0].clone();
+        let games = home(guard.db(), uuid).await?;
+        let game = games[0].games[0]._id;
 
         let form = CreateGameFormResponse {
-            creator: creator.clone(),
-            user: uuid.clone(),
+            creator,
+            user: uuid,
             game,
         };
 
@@ -654,6 +877,77 @@ mod test
     }
 
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_migrate_complete_games() -> Result<(), DatabaseError>
+    {
+        let guard = get_guard().await?;
+        let (white, black, game_id) = create_users_and_game(&guard).await?;
+
+        // The white queen is surrounded
+        let mut board = Board::default();
+        board.place_piece(Piece::new(BoardPiece::Queen, Color::White), (0, 0, 0), None);
+        for sq in shared::r#move::neighbors(&(0, 0, 0))
+        {
+            board.insert(sq, BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black)));
+        }
+
+        let col = guard.db().collection::<Document>(GAMES);
+        let complete = doc! {
+            "players": [white, black],
+            "complete": true,
+            "board": bson::to_bson(&board).unwrap(),
+        };
+        let complete = col.insert_one(complete, None).await?.inserted_id;
+        col.update_one(
+            doc! { "_id": game_id },
+            doc! { "$set": { "complete": false }, "$unset": { "outcome": "" } },
+            None,
+        )
+        .await?;
+
+        migrate(guard.db()).await?;
+        migrate(guard.db()).await?;
+
+        let games = guard.db().collection::<Game>(GAMES);
+        let game = games.find_one(doc! { "_id": complete }, None).await?.unwrap();
+        assert_eq!(game.outcome, Some(GameOutcome::BlackWins(OutcomeReason::QueenSurrounded)));
+
+        let active = get_active_games(guard.db()).await?;
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].game_object_id, game_id);
+
+        assert_eq!(col.count_documents(doc! { "complete": { "$exists": true } }, None).await?, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_migrate_challenges() -> Result<(), DatabaseError>
+    {
+        let guard = get_guard().await?;
+
+        let (id, game) = (ObjectId::new(), ObjectId::new());
+        let user = doc! {
+            "_id": id,
+            "name": "sivert",
+            "password_hash": hash("password"),
+            "create_games": [game],
+            "active_games": [],
+        };
+        guard.db().collection::<Document>(USERS).insert_one(user, None).await?;
+
+        migrate(guard.db()).await?;
+        migrate(guard.db()).await?;
+
+        let challenge = find_user_create_game(guard.db(), &id, &game).await?;
+        assert_eq!(challenge.options, GameOptions::default());
+
+        remove_user_create_game(guard.db(), &id, &game).await?;
+        assert!(find_user_by_id(guard.db(), id).await?.create_games.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn test_cannot_get_non_existing_game() -> Result<(), DatabaseError>
     {
@@ -669,9 +963,13 @@ mod test
     async fn test_can_get_game() -> Result<(), DatabaseError>
     {
         let guard = get_guard().await?;
-        let (_, _, game_id) = create_users_and_game(&guard).await?;
+        let (white, black, game_id) = create_users_and_game(&guard).await?;
 
-        assert!(get_game_by_id(guard.db(), game_id).await.is_ok());
+        // White comes first, whatever order the users are found in
+        let game = get_game_by_id(guard.db(), game_id).await?;
+        let white = find_user_by_id(guard.db(), white).await?.name;
+        let black = find_user_by_id(guard.db(), black).await?.name;
+        assert_eq!(game.players, [white, black]);
 
         Ok(())
     }
@@ -680,23 +978,190 @@ mod test
     async fn test_can_play_move() -> Result<(), DatabaseError>
     {
         let guard = get_guard().await?;
-        let (u1, _, game_id) = create_users_and_game(&guard).await?;
+        let (white, _, game_id) = create_users_and_game(&guard).await?;
 
         let game = get_game_by_id(guard.db(), game_id).await?;
 
         let mov = Move {
-            player_id: u1,
+            player_id: white,
             game_id:   game._id,
             sq:        (0, 0, 0),
             piece:     Piece::new(BoardPiece::Ant, Color::White),
             old_sq:    None,
+            kind:      MoveKind::Regular,
         };
 
-        assert!(play_move(guard.db(), mov).await.is_ok());
+        let played = play_move(guard.db(), mov).await?;
+        assert_eq!(played.len(), 1);
+        assert_eq!(played[0].r#move.piece.ordinal, 1);
+        assert_eq!(played[0].outcome, None);
 
         let game = get_game_by_id(guard.db(), game_id).await?;
         assert_eq!(game.board.len(), 1);
         assert_eq!(game.board.turns, 1);
+        assert_eq!(game.moves.len(), 1);
+        assert_eq!(game.moves[0].r#move, played[0].r#move);
+
+        // The stored board is the one reached by replaying the history
+        let history = game.moves.iter().map(|played| &played.r#move);
+        assert_eq!(Board::replay(game.board.options, history), Ok(game.board));
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cannot_play_illegal_move() -> Result<(), DatabaseError>
+    {
+        let guard = get_guard().await?;
+        let (white, _, game_id) = create_users_and_game(&guard).await?;
+
+        let mov = Move {
+            player_id: white,
+            game_id,
+            sq: (1, -1, 0),
+            piece: Piece::new(BoardPiece::Ant, Color::White),
+            old_sq: None,
+            kind: MoveKind::Regular,
+        };
+
+        let res = play_move(guard.db(), mov).await;
+        assert!(matches!(res, Err(DatabaseError::IllegalMove(MoveError::IllegalSquare))));
+
+        let game = get_game_by_id(guard.db(), game_id).await?;
+        assert_eq!(game.board.len(), 0);
+        assert_eq!(game.board.turns, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cannot_play_out_of_turn() -> Result<(), DatabaseError>
+    {
+        let guard = get_guard().await?;
+        let (_, black, game_id) = create_users_and_game(&guard).await?;
+
+        let mov = Move {
+            player_id: black,
+            game_id,
+            sq: (0, 0, 0),
+            piece: Piece::new(BoardPiece::Ant, Color::Black),
+            old_sq: None,
+            kind: MoveKind::Regular,
+        };
+
+        let res = play_move(guard.db(), mov).await;
+        assert!(matches!(res, Err(DatabaseError::NotYourTurn)));
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_concurrent_moves_are_played_once() -> Result<(), DatabaseError>
+    {
+        let guard = get_guard().await?;
+        let (white, _, game_id) = create_users_and_game(&guard).await?;
+
+        let mov = |sq| Move {
+            player_id: white,
+            game_id,
+            sq,
+            piece: Piece::new(BoardPiece::Ant, Color::White),
+            old_sq: None,
+            kind: MoveKind::Regular,
+        };
+
+        // Both moves are legal on the empty board, but only one can be played
+        let (first, second) = tokio::join!(
+            play_move(guard.db(), mov((0, 0, 0))),
+            play_move(guard.db(), mov((0, 0, 0)))
+        );
+        assert_eq!([first, second].iter().filter(|res| res.is_ok()).count(), 1);
+
+        let game = get_game_by_id(guard.db(), game_id).await?;
+        assert_eq!(game.board.turns, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cannot_play_opponent_piece() -> Result<(), DatabaseError>
+    {
+        let guard = get_guard().await?;
+        let (white, _, game_id) = create_users_and_game(&guard).await?;
+
+        let mov = Move {
+            player_id: white,
+            game_id,
+            sq: (0, 0, 0),
+            piece: Piece::new(BoardPiece::Ant, Color::Black),
+            old_sq: None,
+            kind: MoveKind::Regular,
+        };
+
+        let res = play_move(guard.db(), mov).await;
+        assert!(matches!(res, Err(DatabaseError::NotYourPiece)));
+
+        let game = get_game_by_id(guard.db(), game_id).await?;
+        assert_eq!(game.board.turns, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_can_export_game() -> Result<(), DatabaseError>
+    {
+        let guard = get_guard().await?;
+        let (white, _, game_id) = create_users_and_game(&guard).await?;
+
+        assert_eq!(get_game_string(guard.db(), game_id).await?, "Base;NotStarted;White[1]");
+
+        let mov = Move {
+            player_id: white,
+            game_id,
+            ..Move::new(Piece::new(BoardPiece::Ant, Color::White), (0, 0, 0), None)
+        };
+        play_move(guard.db(), mov).await?;
+
+        assert_eq!(get_game_string(guard.db(), game_id).await?, "Base;InProgress;Black[1];wA1");
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_can_import_game() -> Result<(), DatabaseError>
+    {
+        let guard = get_guard().await?;
+        let user = reg(&guard, "sivert".into()).await?;
+
+        let form = ImportGame {
+            user,
+            game: "Base;InProgress;Black[1];wA1".into(),
+        };
+        let game_id = import_game(guard.db(), form).await?;
+
+        let game = get_game_by_id(guard.db(), game_id).await?;
+        assert_eq!(game.board.turns, 1);
+        assert_eq!(game.players, ["sivert", "sivert"]);
+        assert_eq!(find_user_by_id(guard.db(), user).await?.active_games, vec![game_id]);
+
+        let active = get_active_games(guard.db()).await?;
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].players, ["sivert", "sivert"]);
+
+        // The user plays both sides
+        let mov = Move {
+            player_id: user,
+            game_id,
+            ..Move::new(Piece::new(BoardPiece::Ant, Color::Black), (1, 0, -1), None)
+        };
+        assert!(play_move(guard.db(), mov).await.is_ok());
+
+        let form = ImportGame {
+            user,
+            game: "Base;InProgress;Black[1];wA1;bA1".into(),
+        };
+        let res = import_game(guard.db(), form).await;
+        assert!(matches!(res, Err(DatabaseError::InvalidGameString(_))));
 
         Ok(())
     }
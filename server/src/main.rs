@@ -57,6 +57,7 @@ async fn spawn_http_server(state: State) -> Result<(), SError>
 pub async fn main() -> Result<(), SError>
 {
     let client = database::connect().await?;
+    database::migrate(client.database(database::LIVE)).await?;
     let (tx, rx) = tokio::sync::mpsc::channel(10); // 10 good??
 
     let state = State {
@@ -1,23 +1,253 @@
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
-use shared::model::Board;
+use shared::{
+    model::{
+        Board, Color, GameOptions, GameOutcome, Move, MoveError, MoveKind, OutcomeReason,
+        PlayedMove,
+    },
+    r#move::generate_moves,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct Game
 {
-    players:   [ObjectId; 2],
-    complete:  bool,
-    pub board: Board,
+    pub players:   [ObjectId; 2],
+    pub outcome:   Option<GameOutcome>,
+    pub board:     Board,
+    /// Every move played so far, in order
+    #[serde(default)]
+    pub moves:     Vec<PlayedMove>,
+    /// Both sides are played by the same user, e.g. to study an imported game
+    #[serde(default)]
+    pub analysis:  bool,
+    /// `Board::zobrist` of the position after every move, to tell when one
+    /// is repeated. Stored as `i64`, which is the largest integer BSON has
+    #[serde(default)]
+    pub positions: Vec<i64>,
 }
 
 impl Game
 {
-    pub fn new(players: [ObjectId; 2]) -> Self
+    pub fn new(players: [ObjectId; 2], options: GameOptions) -> Self
     {
         Self {
             players,
-            complete: false,
-            board: Board::default(),
+            outcome: None,
+            board: Board::new(options),
+            moves: Vec::new(),
+            analysis: false,
+            positions: Vec::new(),
         }
     }
+
+    /// A game where `user` plays both sides, starting from the position
+    /// reached by playing `moves`
+    pub fn analysis(user: ObjectId, options: GameOptions, moves: Vec<Move>) -> Self
+    {
+        let mut game = Self {
+            analysis: true,
+            ..Self::new([user, user], options)
+        };
+
+        // The moves were checked when the game string was parsed
+        for r#move in moves
+        {
+            game.board.make_move(r#move.clone());
+            game.record(r#move);
+        }
+
+        // Written down after a pass neither player could move on from, see
+        // `forced_pass`
+        let passed = game.moves.last().is_some_and(|played| played.r#move.kind == MoveKind::Pass);
+        if game.outcome.is_none() && passed && !game.can_move()
+        {
+            game.outcome = Some(GameOutcome::Draw(OutcomeReason::NoLegalMoves));
+        }
+        game
+    }
+
+    /// The first player plays white, the second plays black. In an analysis
+    /// game the player always plays the side to move
+    pub fn color(&self, player: &ObjectId) -> Option<Color>
+    {
+        if self.analysis
+        {
+            return self.players.contains(player).then(|| self.board.to_move());
+        }
+
+        self.players.iter().position(|p| p == player).map(|i| match i
+        {
+            0 => Color::White,
+            _ => Color::Black,
+        })
+    }
+
+    /// Plays a pass for the player to move if they have no legal moves. A
+    /// pass leaves the position as it is, so if the other player can not move
+    /// either nobody ever will, and the game is a draw
+    pub fn forced_pass(&mut self, game_id: ObjectId) -> Option<Move>
+    {
+        if self.outcome.is_some() || self.can_move()
+        {
+            return None;
+        }
+
+        let color = self.board.to_move();
+        let pass = Move::pass(self.players[color as usize], game_id, color);
+        self.play(pass.clone()).ok()?;
+
+        if self.outcome.is_none() && !self.can_move()
+        {
+            self.outcome = Some(GameOutcome::Draw(OutcomeReason::NoLegalMoves));
+        }
+        Some(pass)
+    }
+
+    /// Whether the player to move has any legal move
+    fn can_move(&mut self) -> bool
+    {
+        let color = self.board.to_move();
+        !generate_moves(&mut self.board, color).is_empty()
+    }
+
+    /// Plays `move` and records it in the history of the game. Returns the
+    /// move as it was recorded, with the piece fully identified
+    pub fn play(&mut self, mut r#move: Move) -> Result<Move, MoveError>
+    {
+        if self.outcome.is_some()
+        {
+            return Err(MoveError::GameComplete);
+        }
+
+        // A move of a piece that is not on top keeps the piece it names, so
+        // the board rejects it
+        let piece = self.board.identify(r#move.piece, r#move.old_sq);
+        if r#move.kind != MoveKind::Pass && (r#move.old_sq.is_none() || piece.is(&r#move.piece))
+        {
+            r#move.piece = piece;
+        }
+
+        self.board.play_move(r#move.clone())?;
+        self.record(r#move.clone());
+        Ok(r#move)
+    }
+
+    /// Adds `move`, which has been made on the board, to the history and
+    /// ends the game if it is over. A position seen for the third time with
+    /// the same player to move is a draw
+    fn record(&mut self, r#move: Move)
+    {
+        let position = self.board.zobrist() as i64;
+        self.positions.push(position);
+        self.moves.push(PlayedMove::now(r#move));
+
+        let seen = self.positions.iter().filter(|p| **p == position).count();
+        self.outcome = match self.board.outcome()
+        {
+            None if seen >= 3 => Some(GameOutcome::Draw(OutcomeReason::Repetition)),
+            outcome => outcome,
+        };
+    }
+
+    /// The moves played so far, without when they were played
+    pub fn history(&self) -> Vec<Move>
+    {
+        self.moves.iter().map(|played| played.r#move.clone()).collect()
+    }
+
+    /// Replaying the history from the start leads to the stored board
+    pub fn history_matches(&self) -> bool
+    {
+        let history = self.moves.iter().map(|played| &played.r#move);
+        Board::replay(self.board.options, history).as_ref() == Ok(&self.board)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use shared::{
+        model::{BoardPiece, Piece},
+        notation::parse_move,
+    };
+
+    use super::*;
+
+    fn play(game: &mut Game, moves: &str) -> Result<(), MoveError>
+    {
+        for text in moves.split(';')
+        {
+            let r#move = parse_move(&mut game.board, text).unwrap();
+            game.play(r#move)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_draw_by_repetition()
+    {
+        let user = ObjectId::new();
+        let mut game = Game::new([user, user], GameOptions::default());
+        play(&mut game, "wQ;bQ wQ-;wA1 -wQ;bA1 bQ-").unwrap();
+
+        // The ants go back and forth, back where they started twice
+        let there_and_back = "wA1 /wQ;bA1 bQ/;wA1 -wQ;bA1 bQ-";
+        play(&mut game, there_and_back).unwrap();
+        assert_eq!(game.outcome, None);
+
+        play(&mut game, there_and_back).unwrap();
+        assert_eq!(game.outcome, Some(GameOutcome::Draw(OutcomeReason::Repetition)));
+        assert_eq!(game.positions.len(), game.moves.len());
+
+        assert_eq!(play(&mut game, "wA1 /wQ"), Err(MoveError::GameComplete));
+        assert_eq!(game.forced_pass(ObjectId::new()), None);
+    }
+
+    #[test]
+    fn test_move_must_name_the_piece_on_top()
+    {
+        let user = ObjectId::new();
+        let mut game = Game::new([user, user], GameOptions::default());
+        play(&mut game, "wQ;bQ wQ-;wB1 -wQ;bA1 bQ-;wB1 wQ;bA1 -wB1").unwrap();
+
+        // The white beetle is on top of the white queen
+        let queen = game.board.queens[Color::White as usize];
+        let moves = game.moves.len();
+        let from_queen = |r#type| Move::new(Piece::new(r#type, Color::White), (-1, 0, 1), queen);
+
+        assert_eq!(game.play(from_queen(BoardPiece::Queen)), Err(MoveError::NotOnTop));
+        assert_eq!(game.play(from_queen(BoardPiece::Ant)), Err(MoveError::WrongSourceSquare));
+        assert_eq!(game.moves.len(), moves);
+
+        let played = game.play(from_queen(BoardPiece::Beetle)).unwrap();
+        assert_eq!(played.piece.ordinal, 1);
+    }
+
+    #[test]
+    fn test_draw_when_neither_can_move()
+    {
+        // Not a position a game can reach, but one where neither player has a
+        // move: there are no pieces to move or to place new ones next to
+        let user = ObjectId::new();
+        let mut game = Game::new([user, user], GameOptions::default());
+        game.board.turns = 4;
+
+        let pass = game.forced_pass(ObjectId::new()).unwrap();
+        assert_eq!(pass.piece.color, Color::White);
+        assert_eq!(game.outcome, Some(GameOutcome::Draw(OutcomeReason::NoLegalMoves)));
+        assert_eq!(game.moves.len(), 1);
+
+        assert_eq!(game.forced_pass(ObjectId::new()), None);
+    }
+
+    #[test]
+    fn test_analysis_counts_positions()
+    {
+        let (board, moves) = shared::game_string::opening("Base");
+        let game = Game::analysis(ObjectId::new(), board.options, moves);
+
+        assert_eq!(game.board, board);
+        assert_eq!(game.positions.len(), 4);
+        assert_eq!(game.positions[3], board.zobrist() as i64);
+    }
 }
@@ -1,6 +1,6 @@
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
-use shared::model::UserCredentials;
+use shared::model::{GameChallenge, UserCredentials};
 
 use crate::database::hash;
 
@@ -10,7 +10,7 @@ pub struct User
     pub _id:           ObjectId,
     pub name:          String,
     pub password_hash: String,
-    pub create_games:  Vec<ObjectId>,
+    pub create_games:  Vec<GameChallenge>,
     pub active_games:  Vec<ObjectId>,
 }
 
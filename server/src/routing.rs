@@ -5,11 +5,13 @@ use shared::model::http::*;
 
 mod create_game;
 mod game;
+mod game_string;
 mod home;
 mod login;
 mod register;
 use create_game::create_game;
 use game::game;
+use game_string::game_string;
 use home::home;
 use login::login;
 use register::register;
@@ -51,8 +53,21 @@ pub fn bad_request() -> Body
 
 pub fn error(error: crate::database::DatabaseError) -> Body
 {
-    let body = _body(&format!("{error:?}"));
-    Body::from(ResponseBody::to_body(500, body))
+    use crate::database::DatabaseError::{
+        Conflict, IllegalMove, InvalidGameString, NotYourPiece, NotYourTurn,
+    };
+
+    match error
+    {
+        IllegalMove(e) => Body::from(ResponseBody::to_body(400, _body(e))),
+        InvalidGameString(e) => Body::from(ResponseBody::to_body(400, _body(format!("{e:?}")))),
+        e @ (NotYourTurn | NotYourPiece) =>
+        {
+            Body::from(ResponseBody::to_body(403, _body(format!("{e:?}"))))
+        },
+        e @ Conflict => Body::from(ResponseBody::to_body(409, _body(format!("{e:?}")))),
+        e => Body::from(ResponseBody::to_body(500, _body(format!("{e:?}")))),
+    }
 }
 
 pub fn not_found() -> Body
@@ -109,6 +124,7 @@ async fn handle_request(req: Request<Body>, state: State) -> Response<Body>
             "create-game" => create_game(req, state).await,
             "home" => home(req, state).await,
             "game" => game(req, state).await,
+            "game-string" => game_string(req, state).await,
             _ => Response::new(not_found()),
         }
     }
@@ -1,7 +1,7 @@
 use hyper::{Body, Method, Request, Response};
-use shared::{model::CreateGameFormResponse, ObjectId};
+use shared::model::{CreateGameForm, CreateGameFormResponse};
 
-use super::{create, error, get_body, method_not_allowed};
+use super::{bad_request, create, error, get_body, method_not_allowed};
 use crate::{database, State};
 
 pub async fn create_game(req: Request<Body>, state: State) -> Response<Body>
@@ -10,9 +10,13 @@ pub async fn create_game(req: Request<Body>, state: State) -> Response<Body>
     {
         Method::POST =>
         {
-            let id = get_body::<ObjectId>(req).await.unwrap();
+            let form = match get_body::<CreateGameForm>(req).await
+            {
+                Some(form) => form,
+                None => return Response::new(bad_request()),
+            };
 
-            match database::create_game(state.db(), id).await
+            match database::create_game(state.db(), form.creator, form.options).await
             {
                 Ok(()) => Response::new(create(())),
                 Err(e) => Response::new(error(e)),
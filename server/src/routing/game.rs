@@ -4,9 +4,7 @@ use shared::model::Move;
 
 use super::{bad_request, error, get_body, method_not_allowed, ok};
 use crate::{
-    database::{
-        complete_game, get_active_games, get_game_by_id, play_move, DatabaseError::GameNotComplete,
-    },
+    database::{get_active_games, get_game_by_id, play_move},
     State,
 };
 
@@ -41,18 +39,21 @@ async fn get(req: Request<Body>, state: State) -> Response<Body>
 async fn post(req: Request<Body>, state: State) -> Response<Body>
 {
     let r#move = get_body::<Move>(req).await.unwrap();
-    match play_move(state.db(), r#move.clone()).await
+    match play_move(state.db(), r#move).await
     {
-        Ok(()) =>
+        Ok(played) =>
         {
-            let msg = crate::websocket::Message {
-                r#move,
-            };
-            match state.tx.send(msg).await
+            for update in played
             {
-                Ok(_) => Response::new(ok(())),
-                _ => panic!("websocket server is dead"),
+                let msg = crate::websocket::Message {
+                    update,
+                };
+                if state.tx.send(msg).await.is_err()
+                {
+                    panic!("websocket server is dead");
+                }
             }
+            Response::new(ok(()))
         },
         Err(e) => Response::new(error(e)),
     }
@@ -67,27 +68,6 @@ pub async fn game(req: Request<Body>, state: State) -> Response<Body>
 
         Method::POST => post(req, state).await,
 
-        Method::DELETE =>
-        {
-            let id = get_body::<ObjectId>(req).await.unwrap();
-
-            let db = state.db();
-            let game = match get_game_by_id(db.clone(), id).await
-            {
-                Ok(game) => game,
-                Err(e) => return Response::new(error(e)),
-            };
-
-            if game.board.is_complete()
-            {
-                complete_game(db, id).await.unwrap();
-                Response::new(ok(()))
-            }
-            else
-            {
-                Response::new(error(GameNotComplete))
-            }
-        },
         _ => Response::new(method_not_allowed()),
     }
 }
@@ -0,0 +1,46 @@
+use hyper::{Body, Method, Request, Response};
+use mongodb::bson::oid::ObjectId;
+use shared::model::ImportGame;
+
+use super::{bad_request, create, error, get_body, method_not_allowed, ok};
+use crate::{database, State};
+
+pub async fn game_string(req: Request<Body>, state: State) -> Response<Body>
+{
+    match *req.method()
+    {
+        Method::GET =>
+        {
+            let id = req
+                .uri()
+                .query()
+                .and_then(|q| q.strip_prefix("q="))
+                .and_then(|id| ObjectId::parse_str(id).ok());
+
+            match id
+            {
+                Some(id) => match database::get_game_string(state.db(), id).await
+                {
+                    Ok(game) => Response::new(ok(game)),
+                    Err(e) => Response::new(error(e)),
+                },
+                None => Response::new(bad_request()),
+            }
+        },
+        Method::POST =>
+        {
+            let form = match get_body::<ImportGame>(req).await
+            {
+                Some(form) => form,
+                None => return Response::new(bad_request()),
+            };
+
+            match database::import_game(state.db(), form).await
+            {
+                Ok(id) => Response::new(create(id)),
+                Err(e) => Response::new(error(e)),
+            }
+        },
+        _ => Response::new(method_not_allowed()),
+    }
+}
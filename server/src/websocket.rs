@@ -21,44 +21,46 @@ use tungstenite::{
 
 pub struct Message
 {
-    pub r#move: Move,
+    pub update: GameUpdate,
 }
 
 use std::collections::HashMap;
 
 use mongodb::bson::oid::ObjectId;
-use shared::model::game::Move;
+use shared::model::game::GameUpdate;
 
 #[derive(Default)]
 struct State
 {
-    map: HashMap<ObjectId, Vec<mpsc::Sender<Move>>>,
+    map: HashMap<ObjectId, Vec<mpsc::Sender<GameUpdate>>>,
 }
 
 impl State
 {
-    async fn send_updates(&mut self, r#move: Move)
+    async fn send_updates(&mut self, update: GameUpdate)
     {
-        if let Some(senders) = self.map.remove(&r#move.game_id)
+        let game_id = update.r#move.game_id;
+        if let Some(senders) = self.map.remove(&game_id)
         {
             let new = stream::iter(senders)
                 .filter_map(|tx| {
-                    let mv = r#move.clone();
-                    async move { tx.send(mv).await.ok().map(|_| tx) }
+                    let update = update.clone();
+                    async move { tx.send(update).await.ok().map(|_| tx) }
                 })
-                .collect::<Vec<mpsc::Sender<Move>>>()
+                .collect::<Vec<mpsc::Sender<GameUpdate>>>()
                 .await;
 
-            self.map.insert(r#move.game_id, new);
+            self.map.insert(game_id, new);
         }
     }
 
-    fn add_sender(&mut self, id: ObjectId, sender: mpsc::Sender<Move>)
+    fn add_sender(&mut self, id: ObjectId, sender: mpsc::Sender<GameUpdate>)
     {
         self.map.entry(id).or_default().push(sender);
     }
 }
 
+#[allow(clippy::result_large_err)]
 async fn get_websocket_and_uri(
     stream: TcpStream,
 ) -> Result<(WebSocketStream<TcpStream>, hyper::Uri), tungstenite::Error>
@@ -86,7 +88,7 @@ pub async fn spawn_web_socket_server(mut rx: mpsc::Receiver<Message>)
         select! {
            msg = rx.recv() => {
                if let Some(msg) = msg {
-                state.send_updates(msg.r#move).await;
+                state.send_updates(msg.update).await;
                }
             },
 
@@ -110,7 +112,7 @@ pub async fn spawn_web_socket_server(mut rx: mpsc::Receiver<Message>)
 }
 
 
-async fn handle_connection(mut ws: WebSocketStream<TcpStream>, mut rx: mpsc::Receiver<Move>)
+async fn handle_connection(mut ws: WebSocketStream<TcpStream>, mut rx: mpsc::Receiver<GameUpdate>)
 {
     println!("ENTER");
 
@@ -152,9 +154,9 @@ async fn handle_connection(mut ws: WebSocketStream<TcpStream>, mut rx: mpsc::Rec
             }
 
             msg = rx.recv() => {
-                if let Some(r#move) = msg
+                if let Some(update) = msg
                 {
-                    let text = serde_json::to_string(&r#move).unwrap();
+                    let text = serde_json::to_string(&update).unwrap();
                     let msg = Text(text);
                     if ws.send(msg).await.is_err()
                     {
@@ -1,7 +1,74 @@
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use shared::{model::*, r#move::*};
 
 
+fn occupied(board: &Board, sq: &Square) -> bool
+{
+    matches!(board.get(sq), Some(bs) if !bs.pieces.is_empty())
+}
+
+// The flood fill the one hive rule used to be checked with: move the piece on
+// a copy of the board and check that everything is still connected. Kept here
+// to compare against.
+fn flood_fill(board: &Board, sq: Square, set: &mut Vec<Square>)
+{
+    for sq in neighbors(&sq).into_iter().filter(|sq| occupied(board, sq))
+    {
+        if !set.contains(&sq)
+        {
+            set.push(sq);
+            flood_fill(board, sq, set);
+        }
+    }
+}
+
+fn flood_fill_island(board: &Board, from: Square, to: Square) -> bool
+{
+    let mut board = board.clone();
+    board.play_from_to(from, to);
+
+    let mut set = Vec::with_capacity(board.len());
+    flood_fill(&board, to, &mut set);
+    set.len() < board.len()
+}
+
+/// A ring of pieces `radius` squares from the middle, plus a line of pieces
+/// sticking out of it
+fn large_board(radius: isize) -> Board
+{
+    let mut board = Board::default();
+    let mut sq = (0, -radius, radius);
+
+    for dir in [(1, 0, -1), (0, 1, -1), (-1, 1, 0), (-1, 0, 1), (0, -1, 1), (1, -1, 0)]
+    {
+        for _ in 0..radius
+        {
+            sq = (sq.0 + dir.0, sq.1 + dir.1, sq.2 + dir.2);
+            board.insert(sq, BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black)));
+        }
+    }
+    for i in 1..radius
+    {
+        let sq = (0, -radius - i, radius + i);
+        board.insert(sq, BoardSquare::new(Piece::new(BoardPiece::Ant, Color::White)));
+    }
+    board
+}
+
+/// Every empty square touching the hive
+fn candidates(board: &Board) -> Vec<Square>
+{
+    let mut to = Vec::new();
+    for sq in board.iter().flat_map(|(sq, _)| neighbors(sq))
+    {
+        if !occupied(board, &sq) && !to.contains(&sq)
+        {
+            to.push(sq);
+        }
+    }
+    to
+}
+
 fn criterion_benchmark(c: &mut Criterion)
 {
     let mut board = Board::default();
@@ -37,7 +104,34 @@ fn criterion_benchmark(c: &mut Criterion)
     let from = (-1, -2, 3);
     let to = (-2, -1, 3);
 
-    c.bench_function("create island", |b| b.iter(|| create_island(&mut board, from, to)));
+    c.bench_function("create island", |b| b.iter(|| create_island(&board, from, to)));
+    c.bench_function("create island flood fill", |b| {
+        b.iter(|| flood_fill_island(&board, from, to))
+    });
+
+    // Every destination an ant at the end of the line could try
+    let mut group = c.benchmark_group("one hive");
+    for radius in [3, 6, 10]
+    {
+        let board = large_board(radius);
+        let from = (0, 1 - 2 * radius, 2 * radius - 1);
+        let to = candidates(&board);
+
+        group.bench_with_input(
+            BenchmarkId::new("articulation points", board.len()),
+            &to,
+            |b, to| {
+                b.iter(|| {
+                    let pinned = pinned(&board);
+                    to.iter().filter(|to| keeps_hive(&board, &pinned, from, **to)).count()
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("flood fill", board.len()), &to, |b, to| {
+            b.iter(|| to.iter().filter(|to| !flood_fill_island(&board, from, **to)).count())
+        });
+    }
+    group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
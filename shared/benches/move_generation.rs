@@ -0,0 +1,73 @@
+//! Move generation on the dense grid compared to the `HashMap` the squares
+//! used to be kept in:
+//!
+//! ```sh
+//! cargo bench --bench move_generation --features map-board -- --save-baseline map
+//! cargo bench --bench move_generation -- --baseline map
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use shared::{model::*, r#move::*};
+
+
+/// The position after `plies` moves of a game where every move is picked from
+/// the legal ones in a fixed way, so it is the same every run
+fn position(plies: usize) -> Board
+{
+    let mut board = Board::new(GameOptions {
+        mosquito: true,
+        ladybug: true,
+        pillbug: true,
+        ..Default::default()
+    });
+
+    for i in 0..plies
+    {
+        let color = board.to_move();
+        let mut moves = generate_moves(&mut board, color);
+        if board.is_complete() || moves.is_empty()
+        {
+            break;
+        }
+
+        // The order of the squares differs between the grid and the map
+        moves.sort_by_key(|m| (m.piece.r#type as u8, m.piece.ordinal, m.old_sq, m.sq));
+        board.make_move(moves[i * 7 % moves.len()].clone());
+    }
+    board
+}
+
+fn criterion_benchmark(c: &mut Criterion)
+{
+    let mut group = c.benchmark_group("generate moves");
+    for plies in [10, 30, 60]
+    {
+        let mut board = position(plies);
+        group.bench_function(BenchmarkId::from_parameter(plies), |b| {
+            b.iter(|| {
+                let color = board.to_move();
+                generate_moves(&mut board, color)
+            })
+        });
+    }
+    group.finish();
+
+    let mut board = position(30);
+    let color = board.to_move();
+    let moves = generate_moves(&mut board, color);
+
+    c.bench_function("make and undo", |b| {
+        b.iter(|| {
+            for r#move in &moves
+            {
+                let undo = board.make_move(r#move.clone());
+                board.undo(undo);
+            }
+        })
+    });
+
+    c.bench_function("clone board", |b| b.iter(|| board.clone()));
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
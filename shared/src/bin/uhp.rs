@@ -0,0 +1,421 @@
+//! Talks the Universal Hive Protocol over stdin and stdout, so the rules can
+//! be plugged into Hive GUIs and the test harnesses of other engines.
+//!
+//! Every response ends with a line containing `ok`.
+
+use std::{
+    io::{self, BufRead, Write},
+    time::{Duration, Instant},
+};
+
+use shared::{
+    engine::{mcts, search, Limits, MctsOptions},
+    game_string::*,
+    model::*,
+    notation::*,
+    perft::divide,
+    r#move::generate_moves,
+};
+
+const ID: &str = concat!("id hive ", env!("CARGO_PKG_VERSION"));
+
+/// How many moves ahead `bestmove` looks without a limit
+const DEFAULT_DEPTH: usize = 3;
+
+#[derive(Debug, PartialEq, Eq)]
+enum UhpError
+{
+    /// The command could not be run
+    Err(String),
+    /// The move is not valid in the current position
+    InvalidMove(String),
+}
+
+fn err(e: impl std::fmt::Debug) -> UhpError
+{
+    UhpError::Err(format!("{e:?}"))
+}
+
+fn invalid_move(e: impl std::fmt::Debug) -> UhpError
+{
+    UhpError::InvalidMove(format!("{e:?}"))
+}
+
+/// Which engine `bestmove` asks, set with `options set Engine <name>`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Bot
+{
+    #[default]
+    AlphaBeta,
+    /// Runs its default number of iterations, so `bestmove` takes no limit
+    Mcts,
+}
+
+#[derive(Default)]
+struct Engine
+{
+    options: GameOptions,
+    board:   Option<Board>,
+    /// Every move played so far, ready to be taken back
+    history: Vec<Undo>,
+    bot:     Bot,
+}
+
+impl Engine
+{
+    fn run(&mut self, line: &str) -> Result<String, UhpError>
+    {
+        let (command, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let args = args.trim();
+
+        match command
+        {
+            "info" => Ok(format!("{ID}\nMosquito;Ladybug;Pillbug")),
+            "newgame" => self.new_game(args),
+            "play" => self.play(args),
+            "pass" => self.play("pass"),
+            "validmoves" => self.valid_moves().map(|moves| moves.join(";")),
+            "bestmove" => self.best_move(args),
+            "undo" => self.undo(args),
+            "perft" => self.perft(args),
+            "options" => self.option(args),
+            _ => Err(UhpError::Err(format!("unknown command {command}"))),
+        }
+    }
+
+    fn board(&mut self) -> Result<&mut Board, UhpError>
+    {
+        self.board.as_mut().ok_or_else(|| UhpError::Err("no game in progress".into()))
+    }
+
+    /// Starts a game from a game type like `Base+M`, or from a whole game
+    fn new_game(&mut self, args: &str) -> Result<String, UhpError>
+    {
+        let (options, moves) = match args
+        {
+            "" => (GameOptions::default(), Vec::new()),
+            game if game.contains(';') =>
+            {
+                let (board, moves) = parse_game(game).map_err(err)?;
+                (board.options, moves)
+            },
+            game_type => (parse_game_type(game_type).map_err(err)?, Vec::new()),
+        };
+
+        // The moves have been checked already, play them again to be able to
+        // take them back
+        let mut board = Board::new(options);
+        self.history = moves.into_iter().map(|r#move| board.make_move(r#move)).collect();
+        self.options = options;
+        self.board = Some(board);
+
+        self.game_string()
+    }
+
+    fn play(&mut self, text: &str) -> Result<String, UhpError>
+    {
+        let board = self.board()?;
+
+        let r#move = parse_move(board, text).map_err(invalid_move)?;
+        let undo = board.play_move(r#move).map_err(invalid_move)?;
+
+        self.history.push(undo);
+        self.game_string()
+    }
+
+    fn valid_moves(&mut self) -> Result<Vec<String>, UhpError>
+    {
+        let board = self.board()?;
+        if board.is_complete()
+        {
+            return Err(UhpError::Err("the game is over".into()));
+        }
+
+        let mut moves = Vec::new();
+        for r#move in generate_moves(board, board.to_move())
+        {
+            let written = format_move(board, &r#move);
+            if !moves.contains(&written)
+            {
+                moves.push(written);
+            }
+        }
+
+        if moves.is_empty()
+        {
+            moves.push("pass".into());
+        }
+        Ok(moves)
+    }
+
+    /// Searches for `bestmove depth <n>` moves, or for `bestmove time
+    /// <hh:mm:ss>`
+    fn best_move(&mut self, args: &str) -> Result<String, UhpError>
+    {
+        let limits = match args.split_once(' ')
+        {
+            None if args.is_empty() => Limits::depth(DEFAULT_DEPTH),
+            Some(("depth", n)) =>
+            {
+                Limits::depth(n.parse().map_err(|_| UhpError::Err(format!("not a number {n}")))?)
+            },
+            Some(("time", time)) => Limits::time(parse_time(time)?),
+            _ => return Err(UhpError::Err(format!("unknown limit {args}"))),
+        };
+
+        let bot = self.bot;
+        if bot == Bot::Mcts && !args.is_empty()
+        {
+            return Err(UhpError::Err(format!("{bot:?} does not take a limit")));
+        }
+
+        let board = self.board()?;
+        if board.is_complete()
+        {
+            return Err(UhpError::Err("the game is over".into()));
+        }
+
+        let start = Instant::now();
+        let color = board.to_move();
+        let best = match bot
+        {
+            Bot::AlphaBeta => search(board, color, &limits, || start.elapsed()).best,
+            Bot::Mcts => mcts(board, color, &MctsOptions::default()),
+        };
+
+        Ok(format_move(board, &best.unwrap()))
+    }
+
+    /// Lists the options, or gets or sets one of them. The only one is
+    /// `Engine`
+    fn option(&mut self, args: &str) -> Result<String, UhpError>
+    {
+        match args.split(' ').collect::<Vec<_>>()[..]
+        {
+            [""] | ["get", "Engine"] => (),
+            ["set", "Engine", "AlphaBeta"] => self.bot = Bot::AlphaBeta,
+            ["set", "Engine", "Mcts"] => self.bot = Bot::Mcts,
+            _ => return Err(UhpError::Err(format!("unknown option {args}"))),
+        }
+        Ok(format!("Engine;enum;{:?};AlphaBeta;AlphaBeta;Mcts", self.bot))
+    }
+
+    fn undo(&mut self, args: &str) -> Result<String, UhpError>
+    {
+        let count = match args
+        {
+            "" => 1,
+            n => n.parse::<usize>().map_err(|_| UhpError::Err(format!("not a number {n}")))?,
+        };
+        if count > self.history.len()
+        {
+            self.board()?;
+            return Err(UhpError::Err(format!("only {} moves to undo", self.history.len())));
+        }
+
+        let undos = self.history.split_off(self.history.len() - count);
+        let board = self.board()?;
+        for undo in undos.into_iter().rev()
+        {
+            board.undo(undo);
+        }
+
+        self.game_string()
+    }
+
+    /// The number of positions `depth` moves away for every valid move, and
+    /// the total on the last line
+    fn perft(&mut self, args: &str) -> Result<String, UhpError>
+    {
+        let depth = args
+            .parse::<usize>()
+            .map_err(|_| UhpError::Err(format!("not a number {args}")))?;
+        let board = self.board()?;
+
+        let mut lines = Vec::new();
+        let mut total = 0;
+        for (r#move, count) in divide(board, depth)
+        {
+            lines.push(format!("{} {count}", format_move(board, &r#move)));
+            total += count;
+        }
+        lines.push(total.to_string());
+        Ok(lines.join("\n"))
+    }
+
+    /// The game as `GameType;GameState;Turn;Moves...`
+    fn game_string(&mut self) -> Result<String, UhpError>
+    {
+        self.board()?;
+        let moves = self.history.iter().map(|undo| undo.r#move.clone()).collect::<Vec<_>>();
+        format_game(&self.options, &moves, None).map_err(err)
+    }
+}
+
+/// Parses `hh:mm:ss`
+fn parse_time(time: &str) -> Result<Duration, UhpError>
+{
+    let parts = time
+        .split(':')
+        .map(|part| part.parse::<u64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| UhpError::Err(format!("not a time {time}")))?;
+
+    match parts[..]
+    {
+        [h, m, s] => Ok(Duration::from_secs(h * 3600 + m * 60 + s)),
+        _ => Err(UhpError::Err(format!("not a time {time}"))),
+    }
+}
+
+fn main()
+{
+    let mut engine = Engine::default();
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    let mut respond = |output: Result<String, UhpError>| {
+        match output
+        {
+            Ok(output) if output.is_empty() => (),
+            Ok(output) => writeln!(stdout, "{output}").unwrap(),
+            Err(UhpError::Err(e)) => writeln!(stdout, "err {e}").unwrap(),
+            Err(UhpError::InvalidMove(e)) => writeln!(stdout, "invalidmove {e}").unwrap(),
+        }
+        writeln!(stdout, "ok").unwrap();
+        stdout.flush().unwrap();
+    };
+
+    respond(engine.run("info"));
+
+    for line in stdin.lock().lines()
+    {
+        let line = line.unwrap();
+        match line.trim()
+        {
+            "" => continue,
+            "exit" => break,
+            line => respond(engine.run(line)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn test_play_a_few_moves()
+    {
+        let mut engine = Engine::default();
+
+        assert_eq!(engine.run("newgame Base+M"), Ok("Base+M;NotStarted;White[1]".into()));
+        assert_eq!(engine.run("play wS1"), Ok("Base+M;InProgress;Black[1];wS1".into()));
+        assert_eq!(engine.run("play bM wS1-"), Ok("Base+M;InProgress;White[2];wS1;bM wS1-".into()));
+
+        let moves = engine.run("validmoves").unwrap();
+        assert!(moves.split(';').all(|m| m.starts_with('w')));
+        assert!(moves.split(';').any(|m| m.starts_with("wM ")));
+    }
+
+    #[test]
+    fn test_invalid_moves()
+    {
+        let mut engine = Engine::default();
+
+        assert!(matches!(engine.run("play wS1"), Err(UhpError::Err(_))));
+
+        engine.run("newgame").unwrap();
+        assert_eq!(engine.run("play bS1"), Err(UhpError::InvalidMove("WrongTurn".into())));
+        assert_eq!(engine.run("play wM"), Err(UhpError::InvalidMove("NotInReserve".into())));
+        assert_eq!(engine.run("pass"), Err(UhpError::InvalidMove("PassNotAllowed".into())));
+    }
+
+    #[test]
+    fn test_undo()
+    {
+        let mut engine = Engine::default();
+
+        engine.run("newgame Base").unwrap();
+        engine.run("play wA1").unwrap();
+        engine.run("play bA1 wA1-").unwrap();
+        engine.run("play wQ -wA1").unwrap();
+
+        assert_eq!(engine.run("undo 2"), Ok("Base;InProgress;Black[1];wA1".into()));
+        assert!(matches!(engine.run("undo 2"), Err(UhpError::Err(_))));
+        assert_eq!(engine.run("undo"), Ok("Base;NotStarted;White[1]".into()));
+    }
+
+    #[test]
+    fn test_perft()
+    {
+        let mut engine = Engine::default();
+
+        engine.run("newgame Base").unwrap();
+        let perft = engine.run("perft 2").unwrap();
+        let lines = perft.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines.last(), Some(&"150"));
+        assert!(matches!(engine.run("perft"), Err(UhpError::Err(_))));
+    }
+
+    #[test]
+    fn test_new_game_from_game_string()
+    {
+        let mut engine = Engine::default();
+
+        let game = "Base+M;InProgress;White[2];wS1;bM wS1-";
+        assert_eq!(engine.run(&format!("newgame {game}")), Ok(game.into()));
+        assert_eq!(engine.run("undo"), Ok("Base+M;InProgress;Black[1];wS1".into()));
+
+        assert!(matches!(engine.run("newgame Base;InProgress;White[1]"), Err(UhpError::Err(_))));
+    }
+
+    #[test]
+    fn test_best_move_is_valid()
+    {
+        let mut engine = Engine::default();
+
+        engine.run("newgame").unwrap();
+        for _ in 0..10
+        {
+            let best = engine.run("bestmove depth 2").unwrap();
+            assert!(engine.run("validmoves").unwrap().split(';').any(|m| m == best));
+            engine.run(&format!("play {best}")).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_engine_option()
+    {
+        let mut engine = Engine::default();
+
+        let option = "Engine;enum;AlphaBeta;AlphaBeta;AlphaBeta;Mcts";
+        assert_eq!(engine.run("options"), Ok(option.into()));
+        assert_eq!(
+            engine.run("options set Engine Mcts"),
+            Ok("Engine;enum;Mcts;AlphaBeta;AlphaBeta;Mcts".into())
+        );
+        assert!(matches!(engine.run("options set Engine Minimax"), Err(UhpError::Err(_))));
+        assert_eq!(engine.bot, Bot::Mcts);
+    }
+
+    #[test]
+    fn test_best_move_limits()
+    {
+        let mut engine = Engine::default();
+
+        assert!(matches!(engine.run("bestmove"), Err(UhpError::Err(_))));
+        engine.run("newgame Base").unwrap();
+
+        assert!(engine.run("bestmove time 00:00:01").is_ok());
+        assert!(matches!(engine.run("bestmove time 1s"), Err(UhpError::Err(_))));
+        assert!(matches!(engine.run("bestmove nodes 10"), Err(UhpError::Err(_))));
+
+        engine.run("options set Engine Mcts").unwrap();
+        assert!(matches!(engine.run("bestmove depth 2"), Err(UhpError::Err(_))));
+        assert!(matches!(engine.run("bestmove time 00:00:01"), Err(UhpError::Err(_))));
+    }
+}
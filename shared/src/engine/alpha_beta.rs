@@ -0,0 +1,384 @@
+use std::time::Duration;
+
+use super::{
+    pass,
+    transposition::{Bound, Entry, TranspositionTable},
+    Limits,
+};
+use crate::{
+    eval::evaluate,
+    model::*,
+    r#move::{generate_moves, neighbors},
+};
+
+/// More than any position is worth, less than a won game
+const WIN: i32 = 1_000_000;
+const INFINITY: i32 = WIN + 1;
+
+/// How many positions are visited between looks at the clock
+const CHECK_EVERY: u64 = 1024;
+
+/// How many positions the transposition table of a search holds
+const TABLE_SIZE: usize = 1 << 16;
+
+/// What a search found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult
+{
+    /// The move to play, `None` when `color` is not to move or the game is
+    /// over
+    pub best:  Option<Move>,
+    /// How good the position is for `color`, more than `WIN - MAX_DEPTH` for
+    /// a forced win
+    pub score: i32,
+    /// How many moves ahead the last finished iteration looked
+    pub depth: usize,
+    /// How many positions were visited
+    pub nodes: u64,
+    /// The moves both players are expected to play, starting with `best`
+    pub pv:    Vec<Move>,
+}
+
+/// Looks for the best move for `color` with alpha-beta search, one move
+/// deeper at a time until `limits` are reached. `elapsed` tells how long the
+/// search has been running, for `Limits::time`.
+pub fn search(
+    board: &mut Board,
+    color: Color,
+    limits: &Limits,
+    elapsed: impl Fn() -> Duration,
+) -> SearchResult
+{
+    let mut result = SearchResult {
+        best:  None,
+        score: 0,
+        depth: 0,
+        nodes: 0,
+        pv:    Vec::new(),
+    };
+    if board.is_complete() || board.to_move() != color
+    {
+        return result;
+    }
+
+    let mut search = Search {
+        board,
+        limits,
+        elapsed,
+        nodes: 0,
+        stopped: false,
+        finished: false,
+        killers: Vec::new(),
+        last_pv: Vec::new(),
+        table: TranspositionTable::new(TABLE_SIZE),
+    };
+
+    for depth in 1..=limits.depth.max(1)
+    {
+        let mut pv = Vec::new();
+        let score = search.negamax(depth, 0, -INFINITY, INFINITY, &mut pv);
+        if search.stopped
+        {
+            break;
+        }
+
+        result.best = pv.first().cloned();
+        result.score = score;
+        result.depth = depth;
+        result.pv = pv.clone();
+
+        search.finished = true;
+        search.last_pv = pv;
+
+        // A decided game does not get any better by looking further
+        if score.abs() >= WIN - depth as i32
+        {
+            break;
+        }
+    }
+
+    result.nodes = search.nodes;
+    result
+}
+
+struct Search<'a, F>
+{
+    board:    &'a mut Board,
+    limits:   &'a Limits,
+    elapsed:  F,
+    nodes:    u64,
+    /// Whether a limit was reached, after which every result is thrown away
+    stopped:  bool,
+    /// Whether an iteration has finished, before which the limits are not
+    /// looked at so there always is a move
+    finished: bool,
+    /// Two moves per ply that made the opponent give up on a position before
+    killers:  Vec<[Option<Move>; 2]>,
+    /// The principal variation of the previous iteration
+    last_pv:  Vec<Move>,
+    table:    TranspositionTable,
+}
+
+impl<F> Search<'_, F>
+where
+    F: Fn() -> Duration,
+{
+    /// How good the position is for the player to move, looking `depth`
+    /// moves ahead. The best line found is put into `pv`
+    fn negamax(
+        &mut self,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32
+    {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_EVERY) && self.out_of_budget()
+        {
+            self.stopped = true;
+        }
+        if self.stopped
+        {
+            return 0;
+        }
+
+        let color = self.board.to_move();
+        if let Some(outcome) = self.board.outcome()
+        {
+            // Win sooner and lose later
+            return match outcome.winner()
+            {
+                Some(winner) if winner == color => WIN - ply as i32,
+                Some(_) => ply as i32 - WIN,
+                None => 0,
+            };
+        }
+        if depth == 0
+        {
+            return evaluate(self.board, color).score;
+        }
+
+        // A position seen before, in this iteration or the last, might not
+        // have to be searched again. The root always is, for its move and line
+        let key = self.board.zobrist();
+        let mut table_move = None;
+        if let Some(entry) = self.table.get(key)
+        {
+            let score = from_table(entry.score, ply);
+            let cutoff = match entry.bound
+            {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if ply > 0 && entry.depth >= depth && cutoff
+            {
+                return score;
+            }
+            table_move = entry.best.clone();
+        }
+
+        let mut moves = generate_moves(self.board, color);
+        if moves.is_empty()
+        {
+            moves.push(pass(color));
+        }
+        self.order(&mut moves, ply, table_move.as_ref());
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        for r#move in moves
+        {
+            let mut line = Vec::new();
+            let undo = self.board.make_move(r#move.clone());
+            let score = -self.negamax(depth - 1, ply + 1, -beta, -alpha, &mut line);
+            self.board.undo(undo);
+
+            if self.stopped
+            {
+                return 0;
+            }
+
+            if score > best
+            {
+                best = score;
+                best_move = Some(r#move.clone());
+            }
+            if score > alpha
+            {
+                alpha = score;
+                pv.clear();
+                pv.push(r#move.clone());
+                pv.extend(line);
+            }
+            if alpha >= beta
+            {
+                self.add_killer(ply, r#move);
+                break;
+            }
+        }
+
+        let bound = if best <= original_alpha
+        {
+            Bound::Upper
+        }
+        else if best >= beta
+        {
+            Bound::Lower
+        }
+        else
+        {
+            Bound::Exact
+        };
+        self.table.insert(Entry {
+            key,
+            depth,
+            score: to_table(best, ply),
+            bound,
+            best: best_move,
+        });
+        best
+    }
+
+    fn out_of_budget(&self) -> bool
+    {
+        if !self.finished
+        {
+            return false;
+        }
+        let nodes = self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes);
+        let time = self.limits.time.is_some_and(|time| (self.elapsed)() >= time);
+        nodes || time
+    }
+
+    /// Puts the moves most likely to be best first, so the rest can be cut
+    /// off sooner: the best move from the transposition table, the move of
+    /// the last principal variation, the killers and then moves that close in
+    /// on the opponent's queen
+    fn order(&self, moves: &mut [Move], ply: usize, table_move: Option<&Move>)
+    {
+        let board = &*self.board;
+        let color = board.to_move();
+        let their_queen = board.queens[color.other() as usize];
+        let next_to_queen = |sq: &Square| their_queen.is_some_and(|q| neighbors(&q).contains(sq));
+
+        let pv_move = self.last_pv.get(ply);
+        let killers = self.killers.get(ply);
+
+        moves.sort_by_cached_key(|r#move| {
+            let mut score = 0;
+            if Some(r#move) == table_move
+            {
+                score += 10000;
+            }
+            if Some(r#move) == pv_move
+            {
+                score += 1000;
+            }
+            if killers.is_some_and(|k| k.contains(&Some(r#move.clone())))
+            {
+                score += 100;
+            }
+            if r#move.kind != MoveKind::Pass
+            {
+                let leaves = r#move.old_sq.is_some_and(|sq| next_to_queen(&sq));
+                score += 10 * next_to_queen(&r#move.sq) as i32 - 10 * leaves as i32;
+            }
+            -score
+        });
+    }
+
+    fn add_killer(&mut self, ply: usize, r#move: Move)
+    {
+        if self.killers.len() <= ply
+        {
+            self.killers.resize(ply + 1, [None, None]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0].as_ref() != Some(&r#move)
+        {
+            killers[1] = killers[0].take();
+            killers[0] = Some(r#move);
+        }
+    }
+}
+
+/// Scores of won games count the moves from the root, in the table they
+/// count them from the position itself so they are right wherever it is
+/// reached again
+fn to_table(score: i32, ply: usize) -> i32
+{
+    match score
+    {
+        s if s > WIN / 2 => s + ply as i32,
+        s if s < -WIN / 2 => s - ply as i32,
+        s => s,
+    }
+}
+
+fn from_table(score: i32, ply: usize) -> i32
+{
+    match score
+    {
+        s if s > WIN / 2 => s - ply as i32,
+        s if s < -WIN / 2 => s + ply as i32,
+        s => s,
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::{engine::queen_nearly_surrounded, game_string::opening};
+
+    fn no_clock() -> Duration
+    {
+        Duration::ZERO
+    }
+
+    #[test]
+    fn test_finds_a_move()
+    {
+        let mut board = Board::new(GameOptions::default());
+        let result = search(&mut board, Color::White, &Limits::depth(2), no_clock);
+
+        assert!(result.best.is_some());
+        assert_eq!(result.depth, 2);
+        assert_eq!(result.pv.len(), 2);
+        assert_eq!(board, Board::new(GameOptions::default()));
+    }
+
+    #[test]
+    fn test_not_to_move()
+    {
+        let mut board = Board::new(GameOptions::default());
+        let result = search(&mut board, Color::Black, &Limits::depth(2), no_clock);
+        assert_eq!(result.best, None);
+    }
+
+    #[test]
+    fn test_surrounds_the_queen()
+    {
+        let mut board = queen_nearly_surrounded();
+        let result = search(&mut board, Color::White, &Limits::depth(3), no_clock);
+        assert!(result.score > WIN - 2);
+        assert_eq!(result.depth, 1);
+
+        board.make_move(result.best.unwrap());
+        assert_eq!(board.outcome(), Some(GameOutcome::WhiteWins(OutcomeReason::QueenSurrounded)));
+    }
+
+    #[test]
+    fn test_stops_at_the_node_limit()
+    {
+        let (mut board, _) = opening("Base");
+        let result = search(&mut board, Color::White, &Limits::nodes(2000), no_clock);
+
+        assert!(result.best.is_some());
+        assert!(result.nodes < 2000 + CHECK_EVERY);
+    }
+}
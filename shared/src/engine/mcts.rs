@@ -0,0 +1,320 @@
+use super::{pass, Rng};
+use crate::{
+    model::*,
+    r#move::{generate_moves, neighbors},
+};
+
+/// How the moves of a playout are picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Playout
+{
+    /// Any move, all equally likely
+    #[default]
+    Random,
+    /// A move next to the opponent's queen when there is one, so playouts
+    /// end in a surrounded queen more often
+    Greedy,
+}
+
+/// How an MCTS player searches
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MctsOptions
+{
+    /// How many playouts to run
+    pub iterations:  usize,
+    /// How much to try moves that have not done well yet, `sqrt(2)` in
+    /// plain UCT
+    pub exploration: f64,
+    pub playout:     Playout,
+    /// Playouts that are not decided after this many moves are scored by
+    /// how surrounded the queens are
+    pub max_playout: usize,
+    /// Searches with the same seed pick the same moves
+    pub seed:        u64,
+}
+
+impl Default for MctsOptions
+{
+    fn default() -> Self
+    {
+        Self {
+            iterations:  1000,
+            exploration: std::f64::consts::SQRT_2,
+            playout:     Playout::Random,
+            max_playout: 20,
+            seed:        0,
+        }
+    }
+}
+
+/// Looks for the best move for `color` with Monte Carlo tree search: the
+/// move whose playouts it ran the most. `None` when `color` is not to move
+/// or the game is over
+pub fn mcts(board: &mut Board, color: Color, options: &MctsOptions) -> Option<Move>
+{
+    if board.is_complete() || board.to_move() != color
+    {
+        return None;
+    }
+
+    let mut tree = Tree {
+        nodes:   vec![Node::new(None, color.other())],
+        options: *options,
+        rng:     Rng::new(options.seed),
+    };
+    for _ in 0..options.iterations.max(1)
+    {
+        tree.iterate(board);
+    }
+
+    let root = &tree.nodes[0];
+    root.children
+        .iter()
+        .map(|&child| &tree.nodes[child])
+        .max_by_key(|node| node.visits)
+        .and_then(|node| node.r#move.clone())
+}
+
+struct Node
+{
+    /// The move that led here, `None` for the root
+    r#move:   Option<Move>,
+    /// Who played `move`, whose wins are counted
+    mover:    Color,
+    children: Vec<usize>,
+    /// The moves that have no child yet, `None` before the first visit
+    untried:  Option<Vec<Move>>,
+    visits:   u32,
+    /// 1 for every win of `mover`, a half for every draw
+    wins:     f64,
+}
+
+impl Node
+{
+    fn new(r#move: Option<Move>, mover: Color) -> Self
+    {
+        Self {
+            r#move,
+            mover,
+            children: Vec::new(),
+            untried: None,
+            visits: 0,
+            wins: 0.,
+        }
+    }
+}
+
+struct Tree
+{
+    nodes:   Vec<Node>,
+    options: MctsOptions,
+    rng:     Rng,
+}
+
+impl Tree
+{
+    /// Selects a node, adds a child to it, plays out from there and counts
+    /// the result on the way back up. `board` is the same afterwards
+    fn iterate(&mut self, board: &mut Board)
+    {
+        let mut undos = Vec::new();
+        let mut path = vec![0];
+
+        // Follow the best children down to a node with untried moves
+        let mut node = 0;
+        loop
+        {
+            if board.is_complete()
+            {
+                break;
+            }
+            if self.nodes[node].untried.is_none()
+            {
+                let color = board.to_move();
+                let mut moves = expand_moves(board);
+                if moves.is_empty()
+                {
+                    moves.push(pass(color));
+                }
+                self.nodes[node].untried = Some(moves);
+            }
+
+            let untried = self.nodes[node].untried.as_mut().unwrap();
+            if !untried.is_empty()
+            {
+                let r#move = untried.swap_remove(self.rng.below(untried.len()));
+                let child = self.nodes.len();
+                self.nodes.push(Node::new(Some(r#move.clone()), board.to_move()));
+                self.nodes[node].children.push(child);
+
+                undos.push(board.make_move(r#move));
+                path.push(child);
+                break;
+            }
+
+            node = self.select(node);
+            undos.push(board.make_move(self.nodes[node].r#move.clone().unwrap()));
+            path.push(node);
+        }
+
+        let winner = self.play_out(board, &mut undos);
+
+        for node in path
+        {
+            let node = &mut self.nodes[node];
+            node.visits += 1;
+            node.wins += match winner
+            {
+                Some(color) if color == node.mover => 1.,
+                Some(_) => 0.,
+                None => 0.5,
+            };
+        }
+        for undo in undos.into_iter().rev()
+        {
+            board.undo(undo);
+        }
+    }
+
+    /// The child with the highest upper confidence bound
+    fn select(&self, node: usize) -> usize
+    {
+        let parent = &self.nodes[node];
+        let log_visits = (parent.visits as f64).ln();
+        let uct = |child: usize| {
+            let child = &self.nodes[child];
+            let visits = child.visits as f64;
+            child.wins / visits + self.options.exploration * (log_visits / visits).sqrt()
+        };
+
+        *parent.children.iter().max_by(|a, b| uct(**a).total_cmp(&uct(**b))).unwrap()
+    }
+
+    /// Plays moves by the playout policy until the game is over or the
+    /// playout is long enough, and returns the winner
+    fn play_out(&mut self, board: &mut Board, undos: &mut Vec<Undo>) -> Option<Color>
+    {
+        for _ in 0..self.options.max_playout
+        {
+            if board.is_complete()
+            {
+                break;
+            }
+
+            let color = board.to_move();
+            let moves = expand_moves(board);
+            let r#move = match self.options.playout
+            {
+                _ if moves.is_empty() => pass(color),
+                Playout::Random => moves[self.rng.below(moves.len())].clone(),
+                Playout::Greedy =>
+                {
+                    let queen = board.queens[color.other() as usize];
+                    let attacking = moves
+                        .iter()
+                        .filter(|m| queen.is_some_and(|q| neighbors(&q).contains(&m.sq)))
+                        .collect::<Vec<_>>();
+                    match attacking.len()
+                    {
+                        0 => moves[self.rng.below(moves.len())].clone(),
+                        n => attacking[self.rng.below(n)].clone(),
+                    }
+                },
+            };
+            undos.push(board.make_move(r#move));
+        }
+
+        if let Some(outcome) = board.outcome()
+        {
+            return outcome.winner();
+        }
+
+        // Undecided, so the player whose queen is less surrounded is ahead
+        let around = |color: Color| {
+            board.queens[color as usize].map_or(0, |sq| {
+                neighbors(&sq).into_iter().filter(|sq| board.contains_key(sq)).count()
+            })
+        };
+        match around(Color::White).cmp(&around(Color::Black))
+        {
+            std::cmp::Ordering::Less => Some(Color::White),
+            std::cmp::Ordering::Greater => Some(Color::Black),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+
+/// The moves of the player to move, always in the same order. The order of
+/// `generate_moves` depends on where the squares are kept, which changes when
+/// moves are made and taken back.
+fn expand_moves(board: &mut Board) -> Vec<Move>
+{
+    let mut moves = generate_moves(board, board.to_move());
+    moves.sort_by_key(|m| {
+        let pillbug = match m.kind
+        {
+            MoveKind::Pillbug(sq) => Some(sq),
+            _ => None,
+        };
+        (m.piece.r#type as u8, m.piece.ordinal, m.old_sq, m.sq, pillbug)
+    });
+    moves
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::{engine::queen_nearly_surrounded, game_string::parse_game};
+
+    fn midgame() -> Board
+    {
+        parse_game("Base;InProgress;White[4];wA1;bA1 wA1-;wQ -wA1;bQ bA1-;wG1 \\wQ;bG1 bQ\\")
+            .unwrap()
+            .0
+    }
+
+    fn options(playout: Playout) -> MctsOptions
+    {
+        MctsOptions {
+            iterations: 100,
+            max_playout: 10,
+            playout,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_move()
+    {
+        let mut board = midgame();
+        for playout in [Playout::Random, Playout::Greedy]
+        {
+            let first = mcts(&mut board, Color::White, &options(playout));
+            let second = mcts(&mut board, Color::White, &options(playout));
+
+            assert!(first.is_some());
+            assert_eq!(first, second);
+        }
+        assert_eq!(board, midgame());
+    }
+
+    #[test]
+    fn test_plays_a_legal_move()
+    {
+        let mut board = midgame();
+        let best = mcts(&mut board, Color::White, &options(Playout::Random)).unwrap();
+        assert!(board.play_move(best).is_ok());
+
+        assert_eq!(mcts(&mut board, Color::White, &options(Playout::Random)), None);
+    }
+
+    #[test]
+    fn test_surrounds_the_queen()
+    {
+        let mut board = queen_nearly_surrounded();
+        let best = mcts(&mut board, Color::White, &options(Playout::Greedy)).unwrap();
+        board.make_move(best);
+        assert_eq!(board.outcome(), Some(GameOutcome::WhiteWins(OutcomeReason::QueenSurrounded)));
+    }
+}
@@ -0,0 +1,114 @@
+//! Computer opponents. They only use the board and the move generation, so the
+//! server and the frontend can both run them.
+
+use std::time::Duration;
+
+use crate::{model::*, ObjectId};
+
+mod alpha_beta;
+pub use alpha_beta::{search, SearchResult};
+
+mod mcts;
+pub use mcts::{mcts, MctsOptions, Playout};
+
+mod transposition;
+pub use transposition::{Bound, Entry, TranspositionTable};
+
+/// When a search has to stop and answer with the best move found so far. It
+/// stops at whichever limit it reaches first, but always looks at least one
+/// move ahead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits
+{
+    /// How many moves ahead to look at most
+    pub depth: usize,
+    /// How many positions to visit at most
+    pub nodes: Option<u64>,
+    /// How long to search at most, measured with the clock given to the
+    /// search since `std::time::Instant` is not available in the browser
+    pub time:  Option<Duration>,
+}
+
+impl Limits
+{
+    pub fn depth(depth: usize) -> Self
+    {
+        Self {
+            depth,
+            nodes: None,
+            time: None,
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> Self
+    {
+        Self {
+            nodes: Some(nodes),
+            ..Self::depth(MAX_DEPTH)
+        }
+    }
+
+    pub fn time(time: Duration) -> Self
+    {
+        Self {
+            time: Some(time),
+            ..Self::depth(MAX_DEPTH)
+        }
+    }
+}
+
+/// No search looks further ahead than this
+pub const MAX_DEPTH: usize = 64;
+
+/// A pass by `color`, for positions without any other move
+fn pass(color: Color) -> Move
+{
+    let none = ObjectId::from_bytes([0; 12]);
+    Move::pass(none, none, color)
+}
+
+/// A xorshift generator, random enough for playouts and always the same for
+/// a seed
+pub struct Rng(u64);
+
+impl Rng
+{
+    pub fn new(seed: u64) -> Self
+    {
+        // Spread the seeds out, and never start at 0
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    /// A number from `0` up to but not including `n`
+    pub fn below(&mut self, n: usize) -> usize
+    {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+/// The black queen has five neighbors and the white ant can take the last
+/// square, so white wins in one move
+#[cfg(test)]
+fn queen_nearly_surrounded() -> Board
+{
+    let piece = |r#type, color| BoardSquare::new(Piece::new(r#type, color));
+    let mut board = Board::default();
+    board.from_iter(
+        [
+            ((0, 0, 0), piece(BoardPiece::Queen, Color::Black)),
+            ((1, 0, -1), piece(BoardPiece::Queen, Color::White)),
+            ((1, -1, 0), piece(BoardPiece::Grasshopper, Color::Black)),
+            ((0, -1, 1), piece(BoardPiece::Spider, Color::Black)),
+            ((-1, 0, 1), piece(BoardPiece::Beetle, Color::White)),
+            ((-1, 1, 0), piece(BoardPiece::Grasshopper, Color::White)),
+            ((2, 0, -2), piece(BoardPiece::Ant, Color::White)),
+        ]
+        .into_iter(),
+    );
+    board.queens = [Some((1, 0, -1)), Some((0, 0, 0))];
+    board.turns = 10;
+    board
+}
@@ -0,0 +1,132 @@
+use crate::model::*;
+
+/// Whether a stored score is the score of the position, or only a bound on it
+/// because the search was cut off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound
+{
+    Exact,
+    /// The position is worth at least the score
+    Lower,
+    /// The position is worth at most the score
+    Upper,
+}
+
+/// What a search found out about a position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry
+{
+    /// `Board::zobrist` of the position
+    pub key:   u64,
+    /// How many moves ahead the search looked
+    pub depth: usize,
+    pub score: i32,
+    pub bound: Bound,
+    /// The best move found, tried first when the position comes up again
+    pub best:  Option<Move>,
+}
+
+/// Results of earlier searches, by the Zobrist hash of their position, so a
+/// position reached by moves in another order is not searched again. It has
+/// a fixed number of slots, and a new entry takes over its slot unless the
+/// same position was searched deeper before.
+pub struct TranspositionTable
+{
+    slots: Vec<Option<Entry>>,
+}
+
+impl TranspositionTable
+{
+    pub fn new(capacity: usize) -> Self
+    {
+        Self {
+            slots: vec![None; capacity.max(1)]
+        }
+    }
+
+    fn slot(&self, key: u64) -> usize
+    {
+        (key % self.slots.len() as u64) as usize
+    }
+
+    pub fn get(&self, key: u64) -> Option<&Entry>
+    {
+        self.slots[self.slot(key)].as_ref().filter(|entry| entry.key == key)
+    }
+
+    pub fn insert(&mut self, entry: Entry)
+    {
+        let slot = self.slot(entry.key);
+        let keep = matches!(&self.slots[slot], Some(old) if old.key == entry.key && old.depth > entry.depth);
+        if !keep
+        {
+            self.slots[slot] = Some(entry);
+        }
+    }
+
+    /// How many positions are stored
+    pub fn len(&self) -> usize
+    {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.slots.fill(None);
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn entry(key: u64, depth: usize) -> Entry
+    {
+        Entry {
+            key,
+            depth,
+            score: depth as i32,
+            bound: Bound::Exact,
+            best: None,
+        }
+    }
+
+    #[test]
+    fn test_get_and_insert()
+    {
+        let mut table = TranspositionTable::new(8);
+        assert!(table.is_empty());
+
+        table.insert(entry(3, 2));
+        assert_eq!(table.get(3), Some(&entry(3, 2)));
+        // Same slot, but another position
+        assert_eq!(table.get(11), None);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn test_replacement()
+    {
+        let mut table = TranspositionTable::new(8);
+
+        // A shallower search of the same position does not replace a deeper one
+        table.insert(entry(3, 4));
+        table.insert(entry(3, 2));
+        assert_eq!(table.get(3).map(|e| e.depth), Some(4));
+
+        // Another position does, and the table never grows
+        table.insert(entry(11, 1));
+        assert_eq!(table.get(3), None);
+        assert_eq!(table.get(11).map(|e| e.depth), Some(1));
+        assert_eq!(table.len(), 1);
+
+        table.clear();
+        assert!(table.is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+//! How good a position is for a player, and what that is made of, so bots,
+//! the analysis and post-game reports all agree on it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::*,
+    r#move::{generate_moves, neighbors, pinned},
+};
+
+/// Worth of a free square next to the player's own queen
+const LIBERTY: i32 = 60;
+/// Cost of a piece that can not move without splitting the hive
+const PINNED: i32 = -8;
+/// Worth of a square a piece of this type can move to, in the order of
+/// `BoardPiece::ALL`
+const MOBILITY: [i32; 8] = [6, 1, 2, 3, 2, 2, 2, 3];
+/// Worth of a beetle or mosquito sitting on the opponent's queen
+const ON_QUEEN: i32 = 50;
+/// Worth of a piece still to be placed
+const RESERVE: i32 = 2;
+
+/// The score of a position for one player, with the numbers it comes from
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Evaluation
+{
+    /// How good the position is for the player, above 0 when they are ahead
+    pub score:    i32,
+    pub player:   Terms,
+    pub opponent: Terms,
+}
+
+/// What one side of a position is made of
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct Terms
+{
+    /// Empty squares around the queen, `None` while it is in the reserve
+    pub queen_liberties:  Option<usize>,
+    /// Pieces on top that can not move without splitting the hive
+    pub pinned:           usize,
+    /// How many squares the pieces on the board can move to, by the type of
+    /// piece in the order of `BoardPiece::ALL`
+    pub mobility:         [usize; 8],
+    /// Beetles and mosquitoes on top of the opponent's queen
+    pub beetles_on_queen: usize,
+    /// Pieces still to be placed
+    pub reserve:          usize,
+}
+
+impl Terms
+{
+    /// How much these terms are worth. A queen in the reserve can not be
+    /// surrounded, so it counts as all free
+    pub fn score(&self) -> i32
+    {
+        let mobility = self.mobility.iter().zip(MOBILITY).map(|(n, w)| *n as i32 * w).sum::<i32>();
+
+        LIBERTY * self.queen_liberties.unwrap_or(6) as i32
+            + PINNED * self.pinned as i32
+            + mobility
+            + ON_QUEEN * self.beetles_on_queen as i32
+            + RESERVE * self.reserve as i32
+    }
+
+    /// How many squares pieces of this type can move to
+    pub fn mobility_of(&self, piece: BoardPiece) -> usize
+    {
+        self.mobility[piece as usize]
+    }
+}
+
+/// How good the position is for `color`
+pub fn evaluate(board: &mut Board, color: Color) -> Evaluation
+{
+    let player = terms(board, color);
+    let opponent = terms(board, color.other());
+
+    Evaluation {
+        score: player.score() - opponent.score(),
+        player,
+        opponent,
+    }
+}
+
+fn terms(board: &mut Board, color: Color) -> Terms
+{
+    let mut terms = Terms {
+        queen_liberties: board.queens[color as usize]
+            .map(|sq| neighbors(&sq).into_iter().filter(|sq| !board.contains_key(sq)).count()),
+        ..Default::default()
+    };
+
+    let pinned = pinned(board);
+    let their_queen = board.queens[color.other() as usize];
+    for (sq, _) in board.iter().filter(|(_, bs)| bs.top().color == color)
+    {
+        terms.pinned += pinned.contains(sq) as usize;
+        terms.beetles_on_queen += (Some(*sq) == their_queen) as usize;
+    }
+
+    for r#type in BoardPiece::ALL
+    {
+        terms.reserve += board.in_reserve(&Piece::new(r#type, color));
+    }
+
+    // Only the moves of the pieces themselves, not placements or throws
+    for r#move in generate_moves(board, color)
+    {
+        if r#move.old_sq.is_some() && r#move.kind == MoveKind::Regular
+        {
+            terms.mobility[r#move.piece.r#type as usize] += 1;
+        }
+    }
+    terms
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::game_string::{opening, parse_game};
+
+    #[test]
+    fn test_empty_board()
+    {
+        let mut board = Board::new(GameOptions::default());
+        let eval = evaluate(&mut board, Color::White);
+
+        assert_eq!(eval.score, 0);
+        assert_eq!(eval.player, eval.opponent);
+        assert_eq!(eval.player.reserve, 11);
+        assert_eq!(eval.player.queen_liberties, None);
+    }
+
+    #[test]
+    fn test_terms()
+    {
+        let (mut board, _) =
+            parse_game("Base;InProgress;White[4];wQ;bQ wQ-;wA1 -wQ;bA1 bQ-;wB1 /wQ;bB1 bQ/")
+                .unwrap();
+        let eval = evaluate(&mut board, Color::White);
+        let (white, black) = (&eval.player, &eval.opponent);
+
+        assert_eq!(white.queen_liberties, Some(3));
+        assert_eq!(black.queen_liberties, Some(3));
+        assert_eq!((white.pinned, black.pinned), (1, 1));
+        assert_eq!((white.reserve, black.reserve), (8, 8));
+        assert!(white.mobility_of(BoardPiece::Ant) > 0);
+        assert_eq!(eval.score, white.score() - black.score());
+    }
+
+    #[test]
+    fn test_beetle_on_queen()
+    {
+        let (mut board, _) = parse_game(
+            "Base;InProgress;White[5];wQ;bQ wQ-;wA1 -wQ;bB1 bQ/;wA2 -wA1;bB1 wQ/;wS1 -wA2;bB1 wQ",
+        )
+        .unwrap();
+        let eval = evaluate(&mut board, Color::White);
+
+        assert_eq!(eval.opponent.beetles_on_queen, 1);
+        assert_eq!(eval.player.beetles_on_queen, 0);
+        assert_eq!(eval.player.queen_liberties, Some(4));
+    }
+
+    #[test]
+    fn test_same_for_both_sides()
+    {
+        let (mut board, _) = opening("Base");
+
+        let white = evaluate(&mut board, Color::White);
+        let black = evaluate(&mut board, Color::Black);
+        assert_eq!(white.score, -black.score);
+        assert_eq!(white.player, black.opponent);
+    }
+}
@@ -0,0 +1,261 @@
+//! Whole games written as a Universal Hive Protocol GameString:
+//! `GameType;GameState;Turn;Move;Move...`, e.g.
+//! `Base+M;InProgress;White[2];wS1;bM wS1-`.
+
+use crate::{
+    model::*,
+    notation::{format_game_type, format_move, parse_game_type, parse_move, NotationError},
+    r#move::generate_moves,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameStringError
+{
+    /// Fewer than the three leading fields
+    MissingField,
+    InvalidState(String),
+    InvalidTurn(String),
+    /// The game type, or the move with this index, could not be read
+    Notation(Option<usize>, NotationError),
+    /// The move with this index is not legal
+    IllegalMove(usize, MoveError),
+}
+
+/// `NotStarted`, `InProgress`, `Draw`, `WhiteWins` or `BlackWins`. The
+/// `outcome` of the game goes before the board, which does not show e.g. a
+/// draw by repetition
+pub fn game_state(board: &Board, outcome: Option<GameOutcome>) -> &'static str
+{
+    match (outcome.or_else(|| board.outcome()), board.turns)
+    {
+        (Some(GameOutcome::WhiteWins(_)), _) => "WhiteWins",
+        (Some(GameOutcome::BlackWins(_)), _) => "BlackWins",
+        (Some(GameOutcome::Draw(_)), _) => "Draw",
+        (None, 0) => "NotStarted",
+        (None, _) => "InProgress",
+    }
+}
+
+/// The player to move and the number of the turn, e.g. `Black[3]`
+pub fn turn_string(board: &Board) -> String
+{
+    format!("{:?}[{}]", board.to_move(), board.turns / 2 + 1)
+}
+
+/// Writes a game played with `options` by replaying `moves` from the start.
+/// `outcome` is how the game ended, if it is known, see `game_state`
+pub fn format_game(
+    options: &GameOptions,
+    moves: &[Move],
+    outcome: Option<GameOutcome>,
+) -> Result<String, GameStringError>
+{
+    let mut board = Board::new(*options);
+    let mut written = Vec::with_capacity(moves.len());
+    let mut positions = Vec::with_capacity(moves.len());
+
+    for (i, r#move) in moves.iter().enumerate()
+    {
+        written.push(format_move(&board, r#move));
+        board
+            .play_move(r#move.clone())
+            .map_err(|e| GameStringError::IllegalMove(i, e))?;
+        positions.push(board.zobrist());
+    }
+
+    let outcome = outcome.or_else(|| drawn(&mut board, &positions, moves.last()));
+    let mut fields =
+        vec![format_game_type(options), game_state(&board, outcome).into(), turn_string(&board)];
+    fields.extend(written);
+
+    Ok(fields.join(";"))
+}
+
+/// Reads a game, checking every move against the rules. The state and turn
+/// have to match the position the moves lead to.
+pub fn parse_game(text: &str) -> Result<(Board, Vec<Move>), GameStringError>
+{
+    let mut fields = text.trim().split(';');
+    let mut field = || fields.next().ok_or(GameStringError::MissingField);
+
+    let options = parse_game_type(field()?).map_err(|e| GameStringError::Notation(None, e))?;
+    let state = field()?;
+    let turn = field()?;
+
+    let mut board = Board::new(options);
+    let mut moves = Vec::new();
+    let mut positions = Vec::new();
+
+    for (i, text) in fields.enumerate()
+    {
+        let r#move =
+            parse_move(&mut board, text).map_err(|e| GameStringError::Notation(Some(i), e))?;
+        board
+            .play_move(r#move.clone())
+            .map_err(|e| GameStringError::IllegalMove(i, e))?;
+        moves.push(r#move);
+        positions.push(board.zobrist());
+    }
+
+    let outcome = drawn(&mut board, &positions, moves.last());
+    if state != game_state(&board, outcome)
+    {
+        return Err(GameStringError::InvalidState(state.into()));
+    }
+    if turn != turn_string(&board)
+    {
+        return Err(GameStringError::InvalidTurn(turn.into()));
+    }
+
+    Ok((board, moves))
+}
+
+/// A draw the board does not show: the position after the `last` move has
+/// come up for the third time, or it was a pass and the other player can not
+/// move either. `positions` has the Zobrist key after every move
+fn drawn(board: &mut Board, positions: &[u64], last: Option<&Move>) -> Option<GameOutcome>
+{
+    if board.is_complete()
+    {
+        return None;
+    }
+
+    let repeated = |key: &u64| positions.iter().filter(|p| *p == key).count() >= 3;
+    if positions.last().is_some_and(repeated)
+    {
+        return Some(GameOutcome::Draw(OutcomeReason::Repetition));
+    }
+
+    let passed = last.is_some_and(|r#move| r#move.kind == MoveKind::Pass);
+    let color = board.to_move();
+    match passed && generate_moves(board, color).is_empty()
+    {
+        true => Some(GameOutcome::Draw(OutcomeReason::NoLegalMoves)),
+        false => None,
+    }
+}
+
+/// Both queens placed, each next to an ant, for tests to start from.
+/// `game_type` is the first field of the GameString, e.g. `Base+P`
+#[cfg(any(test, feature = "test-util"))]
+pub fn opening(game_type: &str) -> (Board, Vec<Move>)
+{
+    parse_game(&format!("{game_type};InProgress;White[3];wA1;bA1 wA1-;wQ -wA1;bQ bA1-")).unwrap()
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn test_round_trip()
+    {
+        let text = "Base+M;InProgress;White[3];wS1;bM wS1-;wQ -wS1;bQ bM/";
+
+        let (board, moves) = parse_game(text).unwrap();
+        assert_eq!(board.turns, 4);
+        assert_eq!(moves.len(), 4);
+        assert!(board.options.mosquito);
+
+        assert_eq!(format_game(&board.options, &moves, None), Ok(text.into()));
+    }
+
+    #[test]
+    fn test_new_game()
+    {
+        let (board, moves) = parse_game("Base;NotStarted;White[1]").unwrap();
+        assert!(board.is_empty() && moves.is_empty());
+
+        assert_eq!(
+            format_game(&GameOptions::default(), &[], None),
+            Ok("Base;NotStarted;White[1]".into())
+        );
+    }
+
+    #[test]
+    fn test_finished_game()
+    {
+        // The white queen is surrounded by the last move
+        let text = "Base;BlackWins;White[6];wQ;bQ wQ-;wA1 -wQ;bA1 bQ-;wA2 /wQ;bA2 bQ/;wA3 \
+                    \\wQ;bA1 wQ/;wS1 -wA1;bA2 wQ\\";
+
+        match parse_game(text)
+        {
+            Ok((board, _)) => assert_eq!(game_state(&board, None), "BlackWins"),
+            Err(e) => panic!("{e:?}"),
+        }
+    }
+
+    #[test]
+    fn test_draw_by_repetition()
+    {
+        let (mut board, mut moves) = opening("Base");
+        let queen = |board: &mut Board, color| {
+            let moves = generate_moves(board, color).into_iter();
+            moves
+                .filter(|m| m.piece.r#type == BoardPiece::Queen)
+                .find(|m| m.old_sq.is_some())
+        };
+        let back = |m: &Move| Move::new(m.piece, m.old_sq.unwrap(), Some(m.sq));
+
+        // Both queens step away and back twice, so the position after the
+        // opening comes up for the third time
+        for _ in 0..2
+        {
+            let white = queen(&mut board, Color::White).unwrap();
+            board.make_move(white.clone());
+            let black = queen(&mut board, Color::Black).unwrap();
+            board.make_move(black.clone());
+
+            let shuffle = [white.clone(), black.clone(), back(&white), back(&black)];
+            board.make_move(back(&white));
+            board.make_move(back(&black));
+            moves.extend(shuffle);
+        }
+
+        let text = format_game(&board.options, &moves, None).unwrap();
+        assert!(text.starts_with("Base;Draw;"), "{text}");
+        assert_eq!(parse_game(&text).map(|(_, parsed)| parsed), Ok(moves.clone()));
+        assert_eq!(
+            parse_game(&text.replacen("Draw", "InProgress", 1)).err(),
+            Some(GameStringError::InvalidState("InProgress".into()))
+        );
+
+        // One repetition short the game goes on
+        moves.pop();
+        let text = format_game(&board.options, &moves, None).unwrap();
+        assert!(text.starts_with("Base;InProgress;"), "{text}");
+
+        // The outcome stored with a game goes first
+        let draw = Some(GameOutcome::Draw(OutcomeReason::Repetition));
+        let text = format_game(&board.options, &moves, draw).unwrap();
+        assert!(text.starts_with("Base;Draw;"), "{text}");
+    }
+
+    #[test]
+    fn test_errors()
+    {
+        assert_eq!(parse_game("Base;NotStarted").err(), Some(GameStringError::MissingField));
+        assert_eq!(
+            parse_game("Base;Draw;White[1]").err(),
+            Some(GameStringError::InvalidState("Draw".into()))
+        );
+        assert_eq!(
+            parse_game("Base;InProgress;White[1]").err(),
+            Some(GameStringError::InvalidState("InProgress".into()))
+        );
+        assert_eq!(
+            parse_game("Base;InProgress;White[1];wA1").err(),
+            Some(GameStringError::InvalidTurn("White[1]".into()))
+        );
+        assert_eq!(
+            parse_game("Base;InProgress;White[2];wA1;bA1 wA1-;wA2 wA1-").err(),
+            Some(GameStringError::IllegalMove(2, MoveError::IllegalSquare))
+        );
+        assert!(matches!(
+            parse_game("Base;InProgress;White[2];wA1;bA1 wS1-"),
+            Err(GameStringError::Notation(Some(1), NotationError::NotOnBoard(_)))
+        ));
+    }
+}
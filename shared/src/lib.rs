@@ -1,4 +1,9 @@
+pub mod engine;
+pub mod eval;
+pub mod game_string;
 pub mod model;
 pub mod r#move;
+pub mod notation;
+pub mod perft;
 
 pub use bson::oid::ObjectId;
@@ -1,14 +1,22 @@
-use std::collections::HashMap;
+use std::fmt;
 
 use serde::{Deserialize, Serialize};
-use serde_with::serde_as;
 
-use crate::model::*;
+use crate::model::{squares::Squares, *};
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// The pieces on one square, from the bottom of the stack to the top
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Pieces", into = "Pieces")]
 pub struct BoardSquare
 {
-    pub pieces: Vec<Piece>,
+    pub pieces: Stack,
+}
+
+/// How a `BoardSquare` is stored, e.g. in the database
+#[derive(Serialize, Deserialize)]
+struct Pieces
+{
+    pieces: Vec<Piece>,
 }
 
 impl BoardSquare
@@ -16,13 +24,13 @@ impl BoardSquare
     pub fn new(piece: Piece) -> Self
     {
         Self {
-            pieces: vec![piece]
+            pieces: Stack(pack(piece) as u64)
         }
     }
 
-    pub fn place_piece(&mut self, piece: Piece)
+    pub fn place_piece(&mut self, piece: Piece) -> Result<(), StackFull>
     {
-        self.pieces.push(piece);
+        self.pieces.push(piece)
     }
 
     pub fn remove_piece(&mut self) -> Option<Piece>
@@ -34,23 +42,287 @@ impl BoardSquare
     {
         self.pieces.last().unwrap()
     }
+
+    /// The Zobrist key of this stack at `sq`. Instead of a table of random
+    /// keys for every square, height and piece, which would have no end,
+    /// the square and the packed stack are mixed into one
+    pub(crate) fn zobrist(&self, sq: &Square) -> u64
+    {
+        mix(mix(mix(sq.0 as u64) ^ sq.1 as u64) ^ self.pieces.0)
+    }
 }
 
-// const Y: usize = 2 * (3 * 3 * 2 * 2 * 1);
+/// A stack of pieces packed into one byte per piece, with the bottom piece in
+/// the lowest byte. Eight pieces is more than all beetles and mosquitoes
+/// can climb.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stack(u64);
+
+impl Stack
+{
+    pub const CAPACITY: usize = 8;
+
+    pub fn len(&self) -> usize
+    {
+        (u64::BITS - self.0.leading_zeros()).div_ceil(8) as usize
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.0 == 0
+    }
+
+    pub fn is_full(&self) -> bool
+    {
+        self.len() == Self::CAPACITY
+    }
+
+    pub fn push(&mut self, piece: Piece) -> Result<(), StackFull>
+    {
+        if self.is_full()
+        {
+            return Err(StackFull);
+        }
+        self.0 |= (pack(piece) as u64) << (8 * self.len());
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<Piece>
+    {
+        let top = self.len().checked_sub(1)?;
+        let piece = *unpacked((self.0 >> (8 * top)) as u8);
+        self.0 &= !(0xff << (8 * top));
+        Some(piece)
+    }
+
+    pub fn first(&self) -> Option<&Piece>
+    {
+        self.iter().next()
+    }
+
+    pub fn last(&self) -> Option<&Piece>
+    {
+        self.iter().next_back()
+    }
+
+    pub fn iter(&self) -> Iter
+    {
+        Iter {
+            stack: self.0,
+            range: 0..self.len(),
+        }
+    }
+}
+
+impl IntoIterator for &Stack
+{
+    type IntoIter = Iter;
+    type Item = &'static Piece;
+
+    fn into_iter(self) -> Iter
+    {
+        self.iter()
+    }
+}
+
+/// The pieces of a `Stack`, from the bottom up
+#[derive(Clone)]
+pub struct Iter
+{
+    stack: u64,
+    range: std::ops::Range<usize>,
+}
+
+impl Iterator for Iter
+{
+    type Item = &'static Piece;
+
+    fn next(&mut self) -> Option<&'static Piece>
+    {
+        self.range.next().map(|i| unpacked((self.stack >> (8 * i)) as u8))
+    }
 
-#[serde_as]
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    fn size_hint(&self) -> (usize, Option<usize>)
+    {
+        self.range.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter
+{
+    fn next_back(&mut self) -> Option<&'static Piece>
+    {
+        self.range.next_back().map(|i| unpacked((self.stack >> (8 * i)) as u8))
+    }
+}
+
+impl ExactSizeIterator for Iter {}
+
+impl std::ops::Index<usize> for Stack
+{
+    type Output = Piece;
+
+    fn index(&self, i: usize) -> &Piece
+    {
+        assert!(i < self.len(), "index {i} out of a stack of {}", self.len());
+        unpacked((self.0 >> (8 * i)) as u8)
+    }
+}
+
+impl fmt::Debug for Stack
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// A piece was put on a stack that was already full, which no game gets to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StackFull;
+
+impl fmt::Display for StackFull
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "more than {} pieces on one square", Stack::CAPACITY)
+    }
+}
+
+/// The finalizer of splitmix64, which spreads every bit of `x` over the result
+fn mix(mut x: u64) -> u64
+{
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Added to the Zobrist hash of a position when black is to move
+const BLACK_TO_MOVE: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Mixed with the square of the last moved piece, so its key is not the same
+/// as that of any stack there
+const LAST_MOVED: u64 = 0x6a09_e667_f3bc_c908;
+
+impl fmt::Debug for BoardSquare
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        self.pieces.fmt(f)
+    }
+}
+
+impl TryFrom<Pieces> for BoardSquare
+{
+    type Error = StackFull;
+
+    fn try_from(
+        Pieces {
+            pieces,
+        }: Pieces,
+    ) -> Result<Self, StackFull>
+    {
+        let mut stack = Stack::default();
+        for piece in pieces
+        {
+            stack.push(piece)?;
+        }
+        Ok(Self {
+            pieces: stack
+        })
+    }
+}
+
+impl From<BoardSquare> for Pieces
+{
+    fn from(bs: BoardSquare) -> Self
+    {
+        Self {
+            pieces: bs.pieces.iter().copied().collect()
+        }
+    }
+}
+
+/// `1cttt ooo`: always set, color, type and ordinal
+fn pack(piece: Piece) -> u8
+{
+    0x80 | (piece.color as u8) << 6 | (piece.r#type as u8) << 3 | (piece.ordinal & 0x07)
+}
+
+const fn unpack(byte: u8) -> Piece
+{
+    let color = match byte & 0x40
+    {
+        0 => Color::White,
+        _ => Color::Black,
+    };
+    Piece {
+        r#type: BoardPiece::ALL[(byte >> 3 & 0x07) as usize],
+        color,
+        ordinal: byte & 0x07,
+    }
+}
+
+/// Every piece a byte of a `Stack` can hold, unpacked once so the pieces on
+/// the board can be lent out by reference
+static PIECES: [Piece; 128] = {
+    let mut pieces = [unpack(0); 128];
+    let mut byte = 0;
+    while byte < 128
+    {
+        pieces[byte] = unpack(byte as u8);
+        byte += 1;
+    }
+    pieces
+};
+
+fn unpacked(byte: u8) -> &'static Piece
+{
+    &PIECES[(byte & 0x7f) as usize]
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Board
 {
-    #[serde_as(as = "Vec<(_, _)>")]
-    board: HashMap<Square, BoardSquare>,
+    board: Squares,
 
     pub queens: [Option<Square>; 2],
     pub turns:  usize,
+
+    /// Where the last moved or placed piece ended up. That piece can neither
+    /// move nor be moved by a pillbug on the following turn
+    #[serde(default)]
+    pub last_moved: Option<Square>,
+
+    #[serde(default)]
+    pub options: GameOptions,
 }
 
 impl Board
 {
+    pub fn new(options: GameOptions) -> Self
+    {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    /// The position reached by playing `moves` from the start of a game with
+    /// `options`, or the index of the first illegal move
+    pub fn replay<'a, I>(options: GameOptions, moves: I) -> Result<Self, (usize, MoveError)>
+    where
+        I: IntoIterator<Item = &'a Move>,
+    {
+        let mut board = Self::new(options);
+        for (i, r#move) in moves.into_iter().enumerate()
+        {
+            board.play_move(r#move.clone()).map_err(|e| (i, e))?;
+        }
+        Ok(board)
+    }
+
     pub fn get(&self, sq: &Square) -> Option<&BoardSquare>
     {
         self.board.get(sq)
@@ -66,19 +338,24 @@ impl Board
         self.board.len()
     }
 
+    pub fn is_empty(&self) -> bool
+    {
+        self.board.is_empty()
+    }
+
     pub fn insert(&mut self, sq: Square, bs: BoardSquare)
     {
         self.board.insert(sq, bs);
     }
 
-    pub fn iter(&self) -> std::collections::hash_map::Iter<Square, BoardSquare>
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&Square, &BoardSquare)> + Clone
     {
         self.board.iter()
     }
 
-    pub fn values(&self) -> std::collections::hash_map::Values<Square, BoardSquare>
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &BoardSquare> + Clone
     {
-        self.board.values()
+        self.board.iter().map(|(_, bs)| bs)
     }
 
     pub fn remove(&mut self, sq: Square)
@@ -90,7 +367,29 @@ impl Board
     where
         I: Iterator<Item = (Square, BoardSquare)>,
     {
-        self.board = HashMap::from_iter(iter);
+        self.board = iter.collect();
+    }
+
+    /// A Zobrist hash of the position: which pieces are on which squares, in
+    /// what order they are stacked and whose turn it is. With pillbugs also
+    /// which piece moved last, since it can not be thrown. It is kept up to
+    /// date as pieces are moved, so looking at it costs nothing
+    pub fn zobrist(&self) -> u64
+    {
+        let key = match self.to_move()
+        {
+            Color::White => self.board.zobrist(),
+            Color::Black => self.board.zobrist() ^ BLACK_TO_MOVE,
+        };
+
+        match self.last_moved
+        {
+            Some(sq) if self.options.pillbug =>
+            {
+                key ^ mix(mix(sq.0 as u64 ^ LAST_MOVED) ^ sq.1 as u64)
+            },
+            _ => key,
+        }
     }
 
     pub fn empty_square(&self, sq: &Square) -> bool
@@ -102,80 +401,295 @@ impl Board
         }
     }
 
-    pub fn play_move(&mut self, r#move: Move)
+    /// Puts `piece` on top of the stack at `sq`. No move climbs a full
+    /// stack, see `beetle_move`, so on a board that has one anyway the piece
+    /// is left out rather than crashing the server
+    fn push(&mut self, sq: Square, piece: Piece)
+    {
+        let bs = match self.board.get(&sq).copied()
+        {
+            Some(mut bs) => match bs.place_piece(piece)
+            {
+                Ok(()) => bs,
+                Err(StackFull) => return,
+            },
+            None => BoardSquare::new(piece),
+        };
+        self.board.insert(sq, bs);
+    }
+
+    /// Takes the top piece off the stack at `sq`
+    fn pop(&mut self, sq: Square) -> Option<Piece>
+    {
+        let mut bs = *self.board.get(&sq)?;
+        let piece = bs.remove_piece();
+        if bs.pieces.is_empty()
+        {
+            self.board.remove(&sq);
+        }
+        else
+        {
+            self.board.insert(sq, bs);
+        }
+        piece
+    }
+
+    /// How many pieces of the same type and color as `piece` are still left
+    /// in the player's reserve
+    pub fn in_reserve(&self, piece: &Piece) -> usize
     {
-        // Have logic to check for legal move here
-        self.place_piece(r#move.piece, r#move.sq, r#move.old_sq);
+        self.options.count(piece.r#type).saturating_sub(self.on_board(piece))
     }
 
-    pub fn un_play_from_to(&mut self, from: Square, to: Square)
+    /// How many pieces of the same type and color as `piece` have been placed
+    fn on_board(&self, piece: &Piece) -> usize
     {
-        if let Some(bs) = self.board.get_mut(&to)
+        self.values()
+            .flat_map(|bs| bs.pieces.iter())
+            .filter(|p| p.r#type == piece.r#type && p.color == piece.color)
+            .count()
+    }
+
+    /// The color of the player whose turn it is
+    pub fn to_move(&self) -> Color
+    {
+        match self.turns % 2
+        {
+            0 => Color::White,
+            _ => Color::Black,
+        }
+    }
+
+    /// The color of the player making `move`. A piece moved by a pillbug may
+    /// belong to either player, so the pillbug decides who is moving
+    pub fn mover(&self, r#move: &Move) -> Option<Color>
+    {
+        match r#move.kind
+        {
+            MoveKind::Pillbug(sq) => self.get(&sq).map(|bs| bs.top().color),
+            _ => Some(r#move.piece.color),
+        }
+    }
+
+    /// Checks that `move` is one of the moves produced by
+    /// `r#move::generate_moves` for the current position
+    pub fn check_move(&mut self, r#move: &Move) -> Result<(), MoveError>
+    {
+        if self.is_complete()
+        {
+            return Err(MoveError::GameComplete);
+        }
+
+        match self.mover(r#move)
+        {
+            None => return Err(MoveError::WrongSourceSquare),
+            Some(color) if color != self.to_move() => return Err(MoveError::WrongTurn),
+            _ => (),
+        }
+
+        if r#move.kind == MoveKind::Pass
         {
-            let p = unsafe
+            return match crate::r#move::generate_moves(self, r#move.piece.color).is_empty()
             {
-                bs.remove_piece().unwrap_unchecked()
+                true => Ok(()),
+                false => Err(MoveError::PassNotAllowed),
             };
-            self.board.entry(from).and_modify(|bs| bs.place_piece(p));
+        }
+
+        match r#move.old_sq
+        {
+            Some(sq) =>
+            {
+                let bs = self.get(&sq).ok_or(MoveError::WrongSourceSquare)?;
+                if !bs.top().is(&r#move.piece)
+                {
+                    return Err(match bs.pieces.iter().any(|p| p.is(&r#move.piece))
+                    {
+                        true => MoveError::NotOnTop,
+                        false => MoveError::WrongSourceSquare,
+                    });
+                }
+            },
+            None if matches!(r#move.kind, MoveKind::Pillbug(_)) =>
+            {
+                return Err(MoveError::WrongSourceSquare);
+            },
+            None =>
+            {
+                if self.in_reserve(&r#move.piece) == 0
+                {
+                    return Err(MoveError::NotInReserve);
+                }
+            },
+        }
+
+        let piece = self.identify(r#move.piece, r#move.old_sq);
+        let color = self.to_move();
+        let legal = crate::r#move::generate_moves(self, color).into_iter().any(|m| {
+            m.piece == piece
+                && m.old_sq == r#move.old_sq
+                && m.sq == r#move.sq
+                && m.kind == r#move.kind
+        });
+        match legal
+        {
+            true => Ok(()),
+            false => Err(MoveError::IllegalSquare),
         }
     }
 
-    pub fn play_from_to(&mut self, from: Square, to: Square)
+    /// Plays `move` if it is legal. The returned token takes it back with
+    /// `Board::undo`
+    pub fn play_move(&mut self, r#move: Move) -> Result<Undo, MoveError>
     {
-        let bs = self.board.get_mut(&from).unwrap();
-        let old = bs.remove_piece().unwrap();
-        if bs.pieces.is_empty()
+        self.check_move(&r#move)?;
+        Ok(self.make_move(r#move))
+    }
+
+    /// Plays `move` without checking it against the rules, e.g. for moves
+    /// from `r#move::generate_moves`
+    pub fn make_move(&mut self, mut r#move: Move) -> Undo
+    {
+        let queens = self.queens;
+        let last_moved = self.last_moved;
+
+        match r#move.kind
         {
-            self.board.remove(&from).unwrap();
+            MoveKind::Regular | MoveKind::Pillbug(_) =>
+            {
+                r#move.piece = self.identify(r#move.piece, r#move.old_sq);
+                self.place_piece(r#move.piece, r#move.sq, r#move.old_sq);
+            },
+            MoveKind::Pass =>
+            {
+                self.last_moved = None;
+                self.turns += 1;
+            },
         }
 
-        self.board
-            .entry(to)
-            .and_modify(|bs| bs.place_piece(old))
-            .or_insert_with(|| BoardSquare::new(old));
+        Undo {
+            r#move,
+            queens,
+            last_moved,
+        }
+    }
+
+    /// Takes back the last move, which has to be the one `undo` was returned
+    /// for
+    pub fn undo(&mut self, undo: Undo)
+    {
+        let Undo {
+            r#move,
+            queens,
+            last_moved,
+        } = undo;
+
+        if r#move.kind != MoveKind::Pass
+        {
+            let piece = self.pop(r#move.sq).unwrap();
+            if let Some(old) = r#move.old_sq
+            {
+                self.push(old, piece);
+            }
+        }
+
+        self.queens = queens;
+        self.last_moved = last_moved;
+        self.turns -= 1;
+    }
+
+    pub fn play_from_to(&mut self, from: Square, to: Square)
+    {
+        let piece = self.pop(from).unwrap();
+        self.push(to, piece);
+    }
+
+    /// The piece as it is, or will be, known on the board: the piece on top
+    /// of `old`, or the next piece of its kind from the reserve
+    pub fn identify(&self, piece: Piece, old: Option<Square>) -> Piece
+    {
+        match old.and_then(|sq| self.get(&sq))
+        {
+            Some(bs) => *bs.top(),
+            None => Piece {
+                ordinal: self.on_board(&piece) as u8 + 1,
+                ..piece
+            },
+        }
     }
 
     pub fn place_piece(&mut self, piece: Piece, sq: Square, old: Option<Square>)
     {
+        let piece = self.identify(piece, old);
+
         if piece.r#type == BoardPiece::Queen
         {
             let idx = piece.color as usize;
             self.queens[idx] = Some(sq);
         }
 
-        self.board
-            .entry(sq)
-            .and_modify(|bs| bs.place_piece(piece))
-            .or_insert_with(|| BoardSquare::new(piece));
-
-        if let Some(sq) = old
+        if let Some(old) = old
         {
-            let bs = self.board.get_mut(&sq).unwrap();
-            bs.remove_piece();
-            if bs.pieces.is_empty()
-            {
-                self.board.remove(&sq).unwrap();
-            }
+            self.pop(old);
         }
+        self.push(sq, piece);
 
+        self.last_moved = Some(sq);
         self.turns += 1;
     }
 
     pub fn is_complete(&self) -> bool
     {
-        self.queens.iter().any(|queen| match queen
+        self.outcome().is_some()
+    }
+
+    pub fn outcome(&self) -> Option<GameOutcome>
+    {
+        let surrounded = |color: Color| match self.queens[color as usize]
         {
             Some(sq) =>
             {
-                crate::r#move::neighbors(sq).into_iter().all(|sq| self.board.contains_key(&sq))
+                crate::r#move::neighbors(&sq).into_iter().all(|sq| self.board.contains_key(&sq))
             },
             None => false,
-        })
+        };
+
+        match (surrounded(Color::White), surrounded(Color::Black))
+        {
+            (true, true) => Some(GameOutcome::Draw(OutcomeReason::BothQueensSurrounded)),
+            (true, false) => Some(GameOutcome::BlackWins(OutcomeReason::QueenSurrounded)),
+            (false, true) => Some(GameOutcome::WhiteWins(OutcomeReason::QueenSurrounded)),
+            (false, false) => None,
+        }
     }
 }
 
 
+/// Everything `Board::undo` needs to restore the position before a move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Undo
+{
+    /// The move that was played, with the piece fully identified
+    pub r#move: Move,
+    queens:     [Option<Square>; 2],
+    last_moved: Option<Square>,
+}
+
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError
+{
+    GameComplete,
+    WrongTurn,
+    WrongSourceSquare,
+    NotOnTop,
+    NotInReserve,
+    IllegalSquare,
+    PassNotAllowed,
+}
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BoardPiece
 {
     Queen,
@@ -183,6 +697,67 @@ pub enum BoardPiece
     Spider,
     Beetle,
     Grasshopper,
+    Mosquito,
+    Ladybug,
+    Pillbug,
+}
+
+impl BoardPiece
+{
+    pub const ALL: [BoardPiece; 8] = [
+        BoardPiece::Queen,
+        BoardPiece::Ant,
+        BoardPiece::Spider,
+        BoardPiece::Beetle,
+        BoardPiece::Grasshopper,
+        BoardPiece::Mosquito,
+        BoardPiece::Ladybug,
+        BoardPiece::Pillbug,
+    ];
+
+    /// The letter used for this piece in move notation
+    pub fn letter(&self) -> char
+    {
+        match self
+        {
+            BoardPiece::Queen => 'Q',
+            BoardPiece::Ant => 'A',
+            BoardPiece::Spider => 'S',
+            BoardPiece::Beetle => 'B',
+            BoardPiece::Grasshopper => 'G',
+            BoardPiece::Mosquito => 'M',
+            BoardPiece::Ladybug => 'L',
+            BoardPiece::Pillbug => 'P',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<Self>
+    {
+        Self::ALL.into_iter().find(|piece| piece.letter() == letter)
+    }
+
+    /// Whether this piece is only part of the game when its expansion is
+    /// enabled in `GameOptions`
+    pub fn is_expansion(&self) -> bool
+    {
+        matches!(self, BoardPiece::Mosquito | BoardPiece::Ladybug | BoardPiece::Pillbug)
+    }
+
+    /// The number of pieces of this type each player starts with
+    pub fn count(&self) -> usize
+    {
+        match self
+        {
+            BoardPiece::Queen => 1,
+            BoardPiece::Ant => 3,
+            BoardPiece::Spider => 2,
+            BoardPiece::Beetle => 2,
+            BoardPiece::Grasshopper => 3,
+            BoardPiece::Mosquito => 1,
+            BoardPiece::Ladybug => 1,
+            BoardPiece::Pillbug => 1,
+        }
+    }
 }
 
 impl From<String> for BoardPiece
@@ -197,7 +772,471 @@ impl From<String> for BoardPiece
             "Spider" => Spider,
             "Beetle" => Beetle,
             "Grasshopper" => Grasshopper,
+            "Mosquito" => Mosquito,
+            "Ladybug" => Ladybug,
+            "Pillbug" => Pillbug,
             _ => unreachable!(),
         }
     }
 }
+
+#[cfg(test)]
+mod test
+{
+    use bson::oid::ObjectId;
+
+    use super::*;
+
+    fn mv(piece: Piece, sq: Square, old_sq: Option<Square>) -> Move
+    {
+        Move::new(piece, sq, old_sq)
+    }
+
+    #[test]
+    fn test_play_move_legal_placements()
+    {
+        let mut board = Board::default();
+
+        let white = Piece::new(BoardPiece::Ant, Color::White);
+        let black = Piece::new(BoardPiece::Spider, Color::Black);
+
+        assert!(board.play_move(mv(white, (0, 0, 0), None)).is_ok());
+        assert!(board.play_move(mv(black, (1, -1, 0), None)).is_ok());
+        assert_eq!(board.turns, 2);
+        assert_eq!(board.len(), 2);
+    }
+
+    #[test]
+    fn test_play_move_rejects_illegal_square()
+    {
+        let mut board = Board::default();
+        let ant = Piece::new(BoardPiece::Ant, Color::White);
+
+        assert_eq!(board.play_move(mv(ant, (2, -2, 0), None)), Err(MoveError::IllegalSquare));
+        assert_eq!(board.turns, 0);
+        assert!(board.is_empty());
+    }
+
+    #[test]
+    fn test_play_move_rejects_empty_reserve()
+    {
+        let mut board = Board::default();
+        let queen = Piece::new(BoardPiece::Queen, Color::White);
+
+        board.place_piece(queen, (0, 0, 0), None);
+        board.turns = 2;
+
+        assert_eq!(board.in_reserve(&queen), 0);
+        assert_eq!(board.play_move(mv(queen, (1, -1, 0), None)), Err(MoveError::NotInReserve));
+    }
+
+    #[test]
+    fn test_play_move_rejects_disabled_expansion()
+    {
+        let mosquito = Piece::new(BoardPiece::Mosquito, Color::White);
+
+        let mut board = Board::default();
+        assert_eq!(board.play_move(mv(mosquito, (0, 0, 0), None)), Err(MoveError::NotInReserve));
+
+        let mut board = Board::new(GameOptions {
+            mosquito: true,
+            ..Default::default()
+        });
+        assert!(board.play_move(mv(mosquito, (0, 0, 0), None)).is_ok());
+    }
+
+    #[test]
+    fn test_play_move_rejects_wrong_source()
+    {
+        let mut board = Board::default();
+        let queen = Piece::new(BoardPiece::Queen, Color::White);
+        let ant = Piece::new(BoardPiece::Ant, Color::Black);
+        let beetle = Piece::new(BoardPiece::Beetle, Color::White);
+
+        board.place_piece(queen, (0, 0, 0), None);
+        board.place_piece(ant, (1, -1, 0), None);
+        board.place_piece(beetle, (0, 0, 0), None);
+        board.turns = 4;
+
+        // Nothing at the source square
+        let res = board.play_move(mv(beetle, (0, 1, -1), Some((5, -5, 0))));
+        assert_eq!(res, Err(MoveError::WrongSourceSquare));
+
+        // A different piece at the source square
+        let white_ant = Piece::new(BoardPiece::Ant, Color::White);
+        let res = board.play_move(mv(white_ant, (0, 1, -1), Some((0, 0, 0))));
+        assert_eq!(res, Err(MoveError::WrongSourceSquare));
+
+        // The queen is covered by the beetle
+        let res = board.play_move(mv(queen, (0, 1, -1), Some((0, 0, 0))));
+        assert_eq!(res, Err(MoveError::NotOnTop));
+    }
+
+    #[test]
+    fn test_play_move_rejects_wrong_turn()
+    {
+        let mut board = Board::default();
+        let white = Piece::new(BoardPiece::Ant, Color::White);
+        let black = Piece::new(BoardPiece::Ant, Color::Black);
+
+        assert_eq!(board.to_move(), Color::White);
+        assert_eq!(board.play_move(mv(black, (0, 0, 0), None)), Err(MoveError::WrongTurn));
+
+        assert!(board.play_move(mv(white, (0, 0, 0), None)).is_ok());
+        assert_eq!(board.to_move(), Color::Black);
+        assert_eq!(board.play_move(mv(white, (1, -1, 0), None)), Err(MoveError::WrongTurn));
+    }
+
+    #[test]
+    fn test_pass_only_without_legal_moves()
+    {
+        let mut board = Board::default();
+
+        // The white queen holds the two black ants together, so it can not move
+        // and every empty square next to it touches a black piece
+        let queen = Piece::new(BoardPiece::Queen, Color::White);
+        board.insert((0, 0, 0), BoardSquare::new(queen));
+        for sq in [(1, -1, 0), (-1, 1, 0)]
+        {
+            board.insert(sq, BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black)));
+        }
+        board.queens[Color::White as usize] = Some((0, 0, 0));
+        board.turns = 4;
+
+        assert!(crate::r#move::generate_moves(&mut board, Color::White).is_empty());
+        assert!(!crate::r#move::generate_moves(&mut board, Color::Black).is_empty());
+
+        let pass = |color| Move::pass(ObjectId::new(), ObjectId::new(), color);
+
+        assert!(board.play_move(pass(Color::White)).is_ok());
+        assert_eq!(board.turns, 5);
+        assert_eq!(board.play_move(pass(Color::Black)), Err(MoveError::PassNotAllowed));
+    }
+
+    #[test]
+    fn test_outcome()
+    {
+        let mut board = Board::default();
+        assert_eq!(board.outcome(), None);
+
+        let white = (0, 0, 0);
+        let black = (3, -3, 0);
+        board.place_piece(Piece::new(BoardPiece::Queen, Color::White), white, None);
+        board.place_piece(Piece::new(BoardPiece::Queen, Color::Black), black, None);
+
+        let ant = |color| BoardSquare::new(Piece::new(BoardPiece::Ant, color));
+
+        for sq in crate::r#move::neighbors(&white)
+        {
+            board.insert(sq, ant(Color::Black));
+        }
+        assert_eq!(board.outcome(), Some(GameOutcome::BlackWins(OutcomeReason::QueenSurrounded)));
+        assert!(board.is_complete());
+
+        for sq in crate::r#move::neighbors(&black)
+        {
+            board.insert(sq, ant(Color::White));
+        }
+        assert_eq!(board.outcome(), Some(GameOutcome::Draw(OutcomeReason::BothQueensSurrounded)));
+        assert_eq!(board.outcome().unwrap().winner(), None);
+
+        board.remove((0, 1, -1));
+        assert_eq!(board.outcome(), Some(GameOutcome::WhiteWins(OutcomeReason::QueenSurrounded)));
+        assert_eq!(board.outcome().unwrap().winner(), Some(Color::White));
+    }
+
+    #[test]
+    fn test_pillbug_throw()
+    {
+        let mut board = Board::default();
+
+        let pillbug = Piece::new(BoardPiece::Pillbug, Color::White);
+        let ant = Piece::new(BoardPiece::Ant, Color::Black);
+        let queen = Piece::new(BoardPiece::Queen, Color::White);
+
+        board.place_piece(queen, (-1, 0, 1), None);
+        board.place_piece(ant, (1, 0, -1), None);
+        board.place_piece(pillbug, (0, 0, 0), None);
+        board.place_piece(Piece::new(BoardPiece::Spider, Color::Black), (-2, 0, 2), None);
+
+        let throw = Move {
+            kind: MoveKind::Pillbug((0, 0, 0)),
+            ..mv(ant, (0, 1, -1), Some((1, 0, -1)))
+        };
+        assert_eq!(board.mover(&throw), Some(Color::White));
+
+        // The ant can only be dropped next to the pillbug
+        let far = Move {
+            sq: (1, 1, -2),
+            ..throw.clone()
+        };
+        assert_eq!(board.play_move(far), Err(MoveError::IllegalSquare));
+
+        assert!(board.play_move(throw).is_ok());
+        assert_eq!(board.last_moved, Some((0, 1, -1)));
+        assert!(board.empty_square(&(1, 0, -1)));
+
+        // The thrown ant has to stay put for a turn
+        let frozen = mv(ant, (1, 0, -1), Some((0, 1, -1)));
+        assert_eq!(board.play_move(frozen), Err(MoveError::IllegalSquare));
+    }
+
+    #[test]
+    fn test_pieces_are_numbered_when_placed()
+    {
+        let mut board = Board::default();
+        let ant = Piece::new(BoardPiece::Ant, Color::White);
+        let queen = Piece::new(BoardPiece::Queen, Color::White);
+
+        board.place_piece(ant, (0, 0, 0), None);
+        board.place_piece(Piece::new(BoardPiece::Ant, Color::Black), (1, -1, 0), None);
+        board.place_piece(ant, (-1, 1, 0), None);
+        board.place_piece(queen, (-2, 2, 0), None);
+
+        assert_eq!(board.get(&(0, 0, 0)).unwrap().top().to_string(), "wA1");
+        assert_eq!(board.get(&(1, -1, 0)).unwrap().top().to_string(), "bA1");
+        assert_eq!(board.get(&(-1, 1, 0)).unwrap().top().to_string(), "wA2");
+        assert_eq!(board.get(&(-2, 2, 0)).unwrap().top().to_string(), "wQ");
+
+        // The number stays with the piece when it moves
+        board.place_piece(ant, (-1, 2, -1), Some((-1, 1, 0)));
+        assert_eq!(board.get(&(-1, 2, -1)).unwrap().top().ordinal, 2);
+    }
+
+    #[test]
+    fn test_move_must_name_the_piece_on_top()
+    {
+        let mut board = Board::default();
+        let ant = Piece::new(BoardPiece::Ant, Color::White);
+
+        board.place_piece(Piece::new(BoardPiece::Queen, Color::White), (0, 0, 0), None);
+        board.place_piece(Piece::new(BoardPiece::Queen, Color::Black), (1, -1, 0), None);
+        board.place_piece(ant, (-1, 1, 0), None);
+        board.place_piece(Piece::new(BoardPiece::Ant, Color::Black), (2, -2, 0), None);
+
+        let wa2 = Piece {
+            ordinal: 2,
+            ..ant
+        };
+        let res = board.play_move(mv(wa2, (-1, 0, 1), Some((-1, 1, 0))));
+        assert_eq!(res, Err(MoveError::WrongSourceSquare));
+
+        let wa1 = Piece {
+            ordinal: 1,
+            ..ant
+        };
+        assert!(board.play_move(mv(wa1, (-1, 0, 1), Some((-1, 1, 0)))).is_ok());
+    }
+
+    #[test]
+    fn test_replay()
+    {
+        let white = Piece::new(BoardPiece::Ant, Color::White);
+        let black = Piece::new(BoardPiece::Ant, Color::Black);
+
+        let moves =
+            [mv(white, (0, 0, 0), None), mv(black, (1, 0, -1), None), mv(white, (-1, 0, 1), None)];
+
+        let board = Board::replay(GameOptions::default(), &moves).unwrap();
+        assert_eq!(board.turns, 3);
+        assert_eq!(board.get(&(-1, 0, 1)).unwrap().top().to_string(), "wA2");
+
+        let mut played = Board::default();
+        for r#move in &moves
+        {
+            played.play_move(r#move.clone()).unwrap();
+        }
+        assert_eq!(board, played);
+
+        let res = Board::replay(GameOptions::default(), &moves[1..]);
+        assert_eq!(res, Err((0, MoveError::WrongTurn)));
+    }
+
+    #[test]
+    fn test_undo_restores_the_board()
+    {
+        let mut board = Board::new(GameOptions {
+            mosquito: true,
+            ladybug: true,
+            pillbug: true,
+            ..Default::default()
+        });
+
+        let mut played = Vec::new();
+        for i in 0..30
+        {
+            let color = board.to_move();
+            let moves = crate::r#move::generate_moves(&mut board, color);
+            if board.is_complete() || moves.is_empty()
+            {
+                break;
+            }
+
+            let before = board.clone();
+            let undo = board.play_move(moves[i * 7 % moves.len()].clone()).unwrap();
+            assert_ne!(undo.r#move.piece.ordinal, 0);
+            played.push((before, undo));
+        }
+        assert!(played.len() > 10);
+
+        while let Some((before, undo)) = played.pop()
+        {
+            board.undo(undo);
+            assert_eq!(board, before);
+        }
+        assert_eq!(board.turns, 0);
+        assert!(board.is_empty());
+    }
+
+    #[test]
+    fn test_undo_climb_and_pass()
+    {
+        let mut board = Board::default();
+        let beetle = Piece::new(BoardPiece::Beetle, Color::White);
+        let white = Piece::new(BoardPiece::Queen, Color::White);
+        let black = Piece::new(BoardPiece::Queen, Color::Black);
+        let ant = Piece::new(BoardPiece::Ant, Color::Black);
+
+        board.play_move(mv(white, (0, 0, 0), None)).unwrap();
+        board.play_move(mv(black, (1, 0, -1), None)).unwrap();
+        board.play_move(mv(beetle, (-1, 0, 1), None)).unwrap();
+        board.play_move(mv(ant, (2, 0, -2), None)).unwrap();
+        let before = board.clone();
+
+        let climb = board.play_move(mv(beetle, (0, 0, 0), Some((-1, 0, 1)))).unwrap();
+        assert_eq!(board.len(), 3);
+        board.undo(climb);
+        assert_eq!(board, before);
+
+        let pass = board.make_move(Move::pass(ObjectId::new(), ObjectId::new(), Color::White));
+        assert_eq!(board.last_moved, None);
+        board.undo(pass);
+        assert_eq!(board, before);
+    }
+
+    #[test]
+    fn test_stacks()
+    {
+        let ant = Piece::new(BoardPiece::Ant, Color::White);
+        let beetle = Piece {
+            ordinal: 2,
+            ..Piece::new(BoardPiece::Beetle, Color::Black)
+        };
+
+        let mut bs = BoardSquare::new(ant);
+        assert_eq!(bs.place_piece(beetle), Ok(()));
+        assert_eq!(bs.pieces.len(), 2);
+        assert_eq!(*bs.top(), beetle);
+        assert_eq!(bs.pieces[0], ant);
+        assert_eq!(bs.pieces.iter().copied().collect::<Vec<_>>(), vec![ant, beetle]);
+
+        assert_eq!(bs.remove_piece(), Some(beetle));
+        assert_eq!(bs.remove_piece(), Some(ant));
+        assert!(bs.pieces.is_empty());
+        assert_eq!(bs.remove_piece(), None);
+
+        // A full stack is refused instead of overflowing
+        let mut bs = BoardSquare::new(ant);
+        while !bs.pieces.is_full()
+        {
+            bs.place_piece(beetle).unwrap();
+        }
+        assert_eq!(bs.place_piece(beetle), Err(StackFull));
+        assert_eq!(bs.pieces.len(), Stack::CAPACITY);
+    }
+
+    #[test]
+    fn test_stored_stack_too_high()
+    {
+        let ant = serde_json::json!({ "type": "Ant", "color": "White" });
+        let square = |height| serde_json::json!({ "pieces": vec![&ant; height] });
+
+        assert!(serde_json::from_value::<BoardSquare>(square(Stack::CAPACITY)).is_ok());
+        assert!(serde_json::from_value::<BoardSquare>(square(Stack::CAPACITY + 1)).is_err());
+    }
+
+    #[test]
+    fn test_board_is_stored_as_before()
+    {
+        let mut board = Board::default();
+        board.place_piece(Piece::new(BoardPiece::Queen, Color::White), (0, 0, 0), None);
+        board.place_piece(Piece::new(BoardPiece::Beetle, Color::Black), (0, 0, 0), None);
+
+        // Squares are a list of `(square, { pieces })`
+        let json = serde_json::to_value(&board).unwrap();
+        let pieces = &json["board"][0][1]["pieces"];
+        assert_eq!(json["board"][0][0], serde_json::json!([0, 0, 0]));
+        assert_eq!(pieces.as_array().unwrap().len(), 2);
+        assert_eq!(pieces[1]["type"], "Beetle");
+
+        assert_eq!(serde_json::from_value::<Board>(json).unwrap(), board);
+    }
+
+    #[test]
+    fn test_zobrist()
+    {
+        let (mut board, _) = crate::game_string::opening("Base");
+        let start = board.zobrist();
+
+        // Kept up to date, the same as when it is worked out from scratch
+        let mut rebuilt = Board::default();
+        rebuilt.from_iter(board.iter().map(|(sq, bs)| (*sq, *bs)).collect::<Vec<_>>().into_iter());
+        rebuilt.turns = board.turns;
+        assert_eq!(rebuilt.zobrist(), start);
+
+        let json = serde_json::to_value(&board).unwrap();
+        assert_eq!(serde_json::from_value::<Board>(json).unwrap().zobrist(), start);
+
+        // The same position reached another way
+        board.play_from_to((-1, 0, 1), (0, 1, -1));
+        assert_ne!(board.zobrist(), start);
+        board.play_from_to((0, 1, -1), (-1, 0, 1));
+        assert_eq!(board.zobrist(), start);
+
+        // The other player to move
+        let pass = board.make_move(Move::pass(ObjectId::new(), ObjectId::new(), Color::White));
+        assert_ne!(board.zobrist(), start);
+        board.undo(pass);
+        assert_eq!(board.zobrist(), start);
+    }
+
+    #[test]
+    fn test_zobrist_stacks()
+    {
+        let white = Piece::new(BoardPiece::Beetle, Color::White);
+        let black = Piece::new(BoardPiece::Beetle, Color::Black);
+
+        let stack = |bottom, top| {
+            let mut board = Board::default();
+            board.place_piece(bottom, (0, 0, 0), None);
+            board.place_piece(top, (0, 0, 0), None);
+            board
+        };
+        assert_ne!(stack(white, black).zobrist(), stack(black, white).zobrist());
+
+        // A piece on top of another is not the same as one next to it
+        let mut board = Board::default();
+        board.place_piece(white, (0, 0, 0), None);
+        board.place_piece(black, (1, 0, -1), None);
+        assert_ne!(board.zobrist(), stack(white, black).zobrist());
+
+        board.play_from_to((1, 0, -1), (0, 0, 0));
+        assert_eq!(board.zobrist(), stack(white, black).zobrist());
+    }
+
+    #[test]
+    fn test_zobrist_last_moved()
+    {
+        // Only a pillbug can tell which piece moved last
+        for (game_type, pillbug) in [("Base", false), ("Base+P", true)]
+        {
+            let (mut board, _) = crate::game_string::opening(game_type);
+            let start = board.zobrist();
+
+            board.last_moved = Some((0, 0, 0));
+            assert_eq!(board.zobrist() != start, pillbug);
+            board.last_moved = None;
+            assert_eq!(board.zobrist() != start, pillbug);
+        }
+    }
+}
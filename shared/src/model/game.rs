@@ -1,13 +1,25 @@
-use bson::oid::ObjectId;
+use bson::{oid::ObjectId, DateTime};
 use serde::{Deserialize, Serialize};
 
-use crate::model::board::*;
+use crate::model::{board::*, options::*};
 
 pub type Square = (isize, isize, isize);
 type Name = String;
 
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MoveKind
+{
+    #[default]
+    Regular,
+    /// The player has no legal moves and skips their turn. Only the color of
+    /// `Move::piece` is meaningful for a pass
+    Pass,
+    /// The piece at `Move::old_sq` is moved by the pillbug at this square
+    Pillbug(Square),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct Move
 {
     pub player_id: ObjectId,
@@ -15,6 +27,36 @@ pub struct Move
     pub piece:     Piece,
     pub sq:        Square,
     pub old_sq:    Option<Square>,
+    #[serde(default)]
+    pub kind:      MoveKind,
+}
+
+impl Move
+{
+    /// A regular move that is not tied to any player or game
+    pub fn new(piece: Piece, sq: Square, old_sq: Option<Square>) -> Self
+    {
+        Self {
+            player_id: ObjectId::from_bytes([0; 12]),
+            game_id: ObjectId::from_bytes([0; 12]),
+            piece,
+            sq,
+            old_sq,
+            kind: MoveKind::Regular,
+        }
+    }
+
+    pub fn pass(player_id: ObjectId, game_id: ObjectId, color: Color) -> Self
+    {
+        Self {
+            player_id,
+            game_id,
+            piece: Piece::new(BoardPiece::Queen, color),
+            sq: (0, 0, 0),
+            old_sq: None,
+            kind: MoveKind::Pass,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -28,23 +70,119 @@ pub struct OnGoingGame
 #[derive(Serialize, Deserialize)]
 pub struct GameResource
 {
-    pub _id:     ObjectId,
-    pub players: [Name; 2],
-    pub board:   Board,
+    pub _id:      ObjectId,
+    pub players:  [Name; 2],
+    pub board:    Board,
+    #[serde(default)]
+    pub outcome:  Option<GameOutcome>,
+    #[serde(default)]
+    pub moves:    Vec<PlayedMove>,
+    /// Both sides are played by the same user
+    #[serde(default)]
+    pub analysis: bool,
+}
+
+/// A move in the history of a game, with when it was played
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PlayedMove
+{
+    pub r#move:    Move,
+    pub played_at: DateTime,
+}
+
+impl PlayedMove
+{
+    /// `move`, played right now
+    pub fn now(r#move: Move) -> Self
+    {
+        Self {
+            r#move,
+            played_at: DateTime::now(),
+        }
+    }
+}
+
+/// A move as it is sent to everyone following the game, with the outcome of
+/// the game after it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct GameUpdate
+{
+    pub r#move:  Move,
+    pub outcome: Option<GameOutcome>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum OutcomeReason
+{
+    QueenSurrounded,
+    BothQueensSurrounded,
+    /// The same position came up for the third time, with the same player
+    /// to move
+    Repetition,
+    /// Neither player has a legal move, so the game can not go on
+    NoLegalMoves,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum GameOutcome
+{
+    WhiteWins(OutcomeReason),
+    BlackWins(OutcomeReason),
+    Draw(OutcomeReason),
+}
+
+impl GameOutcome
+{
+    pub fn winner(&self) -> Option<Color>
+    {
+        match self
+        {
+            GameOutcome::WhiteWins(_) => Some(Color::White),
+            GameOutcome::BlackWins(_) => Some(Color::Black),
+            GameOutcome::Draw(_) => None,
+        }
+    }
+
+    pub fn reason(&self) -> OutcomeReason
+    {
+        match *self
+        {
+            GameOutcome::WhiteWins(reason)
+            | GameOutcome::BlackWins(reason)
+            | GameOutcome::Draw(reason) => reason,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Color
 {
     White,
     Black,
 }
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone)]
+impl Color
+{
+    /// The other player's color
+    pub fn other(&self) -> Color
+    {
+        match self
+        {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Piece
 {
-    pub r#type: BoardPiece,
-    pub color:  Color,
+    pub r#type:  BoardPiece,
+    pub color:   Color,
+    /// Tells pieces of the same type and color apart, starting at 1 in the
+    /// order they are placed. 0 means the piece is still in the reserve
+    #[serde(default)]
+    pub ordinal: u8,
 }
 
 impl Piece
@@ -55,7 +193,36 @@ impl Piece
         Self {
             color,
             r#type,
+            ordinal: 0,
+        }
+    }
+
+    /// Whether `other` refers to this piece. A piece without an ordinal
+    /// matches any piece of the same type and color
+    pub fn is(&self, other: &Piece) -> bool
+    {
+        let same_ordinal = self.ordinal == 0 || other.ordinal == 0 || self.ordinal == other.ordinal;
+        self.r#type == other.r#type && self.color == other.color && same_ordinal
+    }
+}
+
+/// Formats the piece like `wA1` or `bQ`. Pieces there is only one of per
+/// player have no number
+impl std::fmt::Display for Piece
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        let color = match self.color
+        {
+            Color::White => 'w',
+            Color::Black => 'b',
+        };
+        write!(f, "{color}{}", self.r#type.letter())?;
+        if self.ordinal > 0 && self.r#type.count() > 1
+        {
+            write!(f, "{}", self.ordinal)?;
         }
+        Ok(())
     }
 }
 
@@ -68,12 +235,38 @@ pub struct CreateGameFormResponse
 }
 
 
+#[derive(Serialize, Deserialize)]
+pub struct CreateGameForm
+{
+    pub creator: ObjectId,
+    pub options: GameOptions,
+}
+
+
+/// A whole game written as a GameString, to be studied by `user`
+#[derive(Serialize, Deserialize)]
+pub struct ImportGame
+{
+    pub user: ObjectId,
+    pub game: String,
+}
+
+
+/// An open challenge, as stored on the user who created it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GameChallenge
+{
+    pub _id:     ObjectId,
+    pub options: GameOptions,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct CreateGameChallenge
 {
     pub name:    String,
     pub creator: ObjectId,
     pub _id:     ObjectId,
+    pub options: GameOptions,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -81,7 +274,7 @@ pub struct CreateGameChallengeBundle
 {
     pub name:       String,
     pub creator_id: ObjectId,
-    pub games:      Vec<ObjectId>,
+    pub games:      Vec<GameChallenge>,
 }
 
 
@@ -9,3 +9,8 @@ pub use game::*;
 
 pub mod board;
 pub use board::*;
+
+mod squares;
+
+pub mod options;
+pub use options::*;
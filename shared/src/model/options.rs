@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::model::*;
+
+/// Who plays white, relative to the player creating the challenge
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhitePlayer
+{
+    #[default]
+    Random,
+    Creator,
+    Opponent,
+}
+
+/// The variant of Hive a game is played with, chosen when the game is created
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GameOptions
+{
+    pub mosquito:   bool,
+    pub ladybug:    bool,
+    pub pillbug:    bool,
+    /// Neither player may place their queen as their first piece
+    pub tournament: bool,
+    pub white:      WhitePlayer,
+}
+
+impl GameOptions
+{
+    /// Whether `piece` is part of this variant
+    pub fn enabled(&self, piece: BoardPiece) -> bool
+    {
+        match piece
+        {
+            BoardPiece::Mosquito => self.mosquito,
+            BoardPiece::Ladybug => self.ladybug,
+            BoardPiece::Pillbug => self.pillbug,
+            _ => true,
+        }
+    }
+
+    /// How many pieces of type `piece` each player starts with
+    pub fn count(&self, piece: BoardPiece) -> usize
+    {
+        match self.enabled(piece)
+        {
+            true => piece.count(),
+            false => 0,
+        }
+    }
+
+    /// Every piece type that is part of this variant
+    pub fn pieces(&self) -> impl Iterator<Item = BoardPiece> + '_
+    {
+        BoardPiece::ALL.into_iter().filter(|piece| self.enabled(*piece))
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn test_base_game_has_no_expansions()
+    {
+        let options = GameOptions::default();
+
+        assert_eq!(options.pieces().count(), 5);
+        assert_eq!(options.count(BoardPiece::Ant), 3);
+        assert_eq!(options.count(BoardPiece::Mosquito), 0);
+    }
+
+    #[test]
+    fn test_expansions_can_be_enabled()
+    {
+        let options = GameOptions {
+            ladybug: true,
+            pillbug: true,
+            ..Default::default()
+        };
+
+        assert_eq!(options.pieces().count(), 7);
+        assert_eq!(options.count(BoardPiece::Ladybug), 1);
+        assert_eq!(options.count(BoardPiece::Mosquito), 0);
+    }
+}
@@ -0,0 +1,394 @@
+//! Where a `Board` keeps its squares. By default that is a dense grid indexed
+//! by axial coordinates, with the `map-board` feature it is a `HashMap` like
+//! it used to be, which the benches compare the grid against.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::*;
+
+#[cfg(not(feature = "map-board"))]
+pub type Squares = Grid;
+
+#[cfg(feature = "map-board")]
+pub type Squares = Map;
+
+/// Width and height of the grid. No hive is longer than 28 pieces, so it
+/// always fits once it is re-centered
+const SIZE: usize = 32;
+
+/// The cell of a square too far down `squares` for a `u8` to point at
+const FAR: u8 = u8::MAX;
+
+/// The cell pointing at `squares[i]`
+fn cell(i: usize) -> u8
+{
+    u8::try_from(i + 1).ok().filter(|cell| *cell != FAR).unwrap_or(FAR)
+}
+
+/// The occupied squares, with a cell for every square in a `SIZE` by `SIZE`
+/// rhombus pointing into them. When a piece would end up outside of the
+/// rhombus it is moved so the hive is in the middle of it again.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "Vec<(Square, BoardSquare)>", into = "Vec<(Square, BoardSquare)>")]
+pub struct Grid
+{
+    /// Axial `(q, r)` of the first cell
+    origin:  (isize, isize),
+    /// One more than the index into `squares` of every cell, 0 when empty
+    cells:   Box<[u8; SIZE * SIZE]>,
+    squares: Vec<(Square, BoardSquare)>,
+    /// The Zobrist keys of all squares combined
+    zobrist: u64,
+}
+
+impl Grid
+{
+    fn index(&self, sq: &Square) -> Option<usize>
+    {
+        let q = usize::try_from(sq.0 - self.origin.0).ok().filter(|q| *q < SIZE)?;
+        let r = usize::try_from(sq.1 - self.origin.1).ok().filter(|r| *r < SIZE)?;
+        Some(r * SIZE + q)
+    }
+
+    fn position(&self, sq: &Square) -> Option<usize>
+    {
+        match self.index(sq).map(|i| self.cells[i])
+        {
+            Some(0) => None,
+            Some(FAR) | None => self.squares.iter().position(|(s, _)| s == sq),
+            Some(cell) => Some(cell as usize - 1),
+        }
+    }
+
+    /// Moves the grid so the hive and `sq` are in the middle of it. No game
+    /// gets a hive too big for the grid, but a stored board could have one,
+    /// so the squares without a cell are looked for in `squares` instead
+    fn recenter(&mut self, sq: &Square)
+    {
+        let (mut min, mut max) = ((sq.0, sq.1), (sq.0, sq.1));
+        for (sq, _) in &self.squares
+        {
+            min = (min.0.min(sq.0), min.1.min(sq.1));
+            max = (max.0.max(sq.0), max.1.max(sq.1));
+        }
+
+        // Leave as much room on either side
+        let slack = |min: isize, max: isize| (SIZE as isize - 1 - (max - min)) / 2;
+        self.origin = (min.0 - slack(min.0, max.0), min.1 - slack(min.1, max.1));
+
+        self.cells.fill(0);
+        for i in 0..self.squares.len()
+        {
+            if let Some(index) = self.index(&self.squares[i].0)
+            {
+                self.cells[index] = cell(i);
+            }
+        }
+    }
+
+    pub fn get(&self, sq: &Square) -> Option<&BoardSquare>
+    {
+        self.position(sq).map(|i| &self.squares[i].1)
+    }
+
+    pub fn contains_key(&self, sq: &Square) -> bool
+    {
+        self.position(sq).is_some()
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.squares.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.squares.is_empty()
+    }
+
+    pub fn zobrist(&self) -> u64
+    {
+        self.zobrist
+    }
+
+    pub fn insert(&mut self, sq: Square, bs: BoardSquare)
+    {
+        self.zobrist ^= bs.zobrist(&sq);
+        if let Some(i) = self.position(&sq)
+        {
+            self.zobrist ^= self.squares[i].1.zobrist(&sq);
+            self.squares[i].1 = bs;
+            return;
+        }
+        if self.index(&sq).is_none()
+        {
+            self.recenter(&sq);
+        }
+
+        self.squares.push((sq, bs));
+        if let Some(index) = self.index(&sq)
+        {
+            self.cells[index] = cell(self.squares.len() - 1);
+        }
+    }
+
+    pub fn remove(&mut self, sq: &Square) -> Option<BoardSquare>
+    {
+        let i = self.position(sq)?;
+        if let Some(index) = self.index(sq)
+        {
+            self.cells[index] = 0;
+        }
+
+        let (_, bs) = self.squares.swap_remove(i);
+        self.zobrist ^= bs.zobrist(sq);
+        if let Some(index) = self.squares.get(i).and_then(|(moved, _)| self.index(moved))
+        {
+            self.cells[index] = cell(i);
+        }
+        Some(bs)
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&Square, &BoardSquare)> + Clone
+    {
+        self.squares.iter().map(|(sq, bs)| (sq, bs))
+    }
+}
+
+impl Default for Grid
+{
+    fn default() -> Self
+    {
+        let half = SIZE as isize / 2;
+        Self {
+            origin:  (-half, -half),
+            cells:   Box::new([0; SIZE * SIZE]),
+            squares: Vec::new(),
+            zobrist: 0,
+        }
+    }
+}
+
+/// Grids with the same squares are equal, no matter where they were moved
+impl PartialEq for Grid
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        self.len() == other.len() && self.iter().all(|(sq, bs)| other.get(sq) == Some(bs))
+    }
+}
+
+impl Eq for Grid {}
+
+impl fmt::Debug for Grid
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl FromIterator<(Square, BoardSquare)> for Grid
+{
+    fn from_iter<I: IntoIterator<Item = (Square, BoardSquare)>>(iter: I) -> Self
+    {
+        let mut grid = Self::default();
+        for (sq, bs) in iter
+        {
+            grid.insert(sq, bs);
+        }
+        grid
+    }
+}
+
+impl From<Vec<(Square, BoardSquare)>> for Grid
+{
+    fn from(squares: Vec<(Square, BoardSquare)>) -> Self
+    {
+        squares.into_iter().collect()
+    }
+}
+
+impl From<Grid> for Vec<(Square, BoardSquare)>
+{
+    fn from(grid: Grid) -> Self
+    {
+        grid.squares
+    }
+}
+
+/// The squares in a `HashMap`
+#[cfg(feature = "map-board")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<(Square, BoardSquare)>", into = "Vec<(Square, BoardSquare)>")]
+pub struct Map
+{
+    squares: std::collections::HashMap<Square, BoardSquare>,
+    zobrist: u64,
+}
+
+#[cfg(feature = "map-board")]
+impl Map
+{
+    pub fn get(&self, sq: &Square) -> Option<&BoardSquare>
+    {
+        self.squares.get(sq)
+    }
+
+    pub fn contains_key(&self, sq: &Square) -> bool
+    {
+        self.squares.contains_key(sq)
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.squares.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.squares.is_empty()
+    }
+
+    pub fn zobrist(&self) -> u64
+    {
+        self.zobrist
+    }
+
+    pub fn insert(&mut self, sq: Square, bs: BoardSquare)
+    {
+        self.zobrist ^= bs.zobrist(&sq);
+        if let Some(old) = self.squares.insert(sq, bs)
+        {
+            self.zobrist ^= old.zobrist(&sq);
+        }
+    }
+
+    pub fn remove(&mut self, sq: &Square) -> Option<BoardSquare>
+    {
+        let bs = self.squares.remove(sq)?;
+        self.zobrist ^= bs.zobrist(sq);
+        Some(bs)
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&Square, &BoardSquare)> + Clone
+    {
+        self.squares.iter()
+    }
+}
+
+#[cfg(feature = "map-board")]
+impl FromIterator<(Square, BoardSquare)> for Map
+{
+    fn from_iter<I: IntoIterator<Item = (Square, BoardSquare)>>(iter: I) -> Self
+    {
+        let mut map = Self::default();
+        for (sq, bs) in iter
+        {
+            map.insert(sq, bs);
+        }
+        map
+    }
+}
+
+#[cfg(feature = "map-board")]
+impl From<Vec<(Square, BoardSquare)>> for Map
+{
+    fn from(squares: Vec<(Square, BoardSquare)>) -> Self
+    {
+        squares.into_iter().collect()
+    }
+}
+
+#[cfg(feature = "map-board")]
+impl From<Map> for Vec<(Square, BoardSquare)>
+{
+    fn from(map: Map) -> Self
+    {
+        map.squares.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn ant() -> BoardSquare
+    {
+        BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))
+    }
+
+    #[test]
+    fn test_insert_and_remove()
+    {
+        let mut grid = Grid::default();
+        for sq in [(0, 0, 0), (1, 0, -1), (2, 0, -2)]
+        {
+            grid.insert(sq, ant());
+        }
+
+        assert_eq!(grid.remove(&(0, 0, 0)), Some(ant()));
+        assert_eq!(grid.remove(&(0, 0, 0)), None);
+
+        // The last square took the place of the removed one
+        assert_eq!(grid.len(), 2);
+        assert!(grid.contains_key(&(1, 0, -1)) && grid.contains_key(&(2, 0, -2)));
+    }
+
+    #[test]
+    fn test_recenter()
+    {
+        let mut grid = Grid::default();
+        for q in 0..20
+        {
+            grid.insert((q, 0, -q), ant());
+        }
+
+        // Crawl far past where the grid started
+        for q in 20..200
+        {
+            grid.insert((q, 0, -q), ant());
+            grid.remove(&(q - 20, 0, 20 - q));
+        }
+
+        assert_eq!(grid.len(), 20);
+        assert!((180..200).all(|q| grid.contains_key(&(q, 0, -q))));
+        assert!(!grid.contains_key(&(0, 0, 0)));
+
+        // A hive as long as the grid is wide still fits
+        for q in 200..180 + SIZE as isize
+        {
+            grid.insert((q, 0, -q), ant());
+        }
+        assert_eq!(grid.len(), SIZE);
+    }
+
+    #[test]
+    fn test_too_big_for_the_grid()
+    {
+        // Longer than the grid is wide, and more squares than a cell points at
+        let squares = (0..300).map(|q| ((q, 0, -q), ant())).collect::<Vec<_>>();
+        let mut grid = Grid::from(squares);
+
+        assert_eq!(grid.len(), 300);
+        assert!((0..300).all(|q| grid.get(&(q, 0, -q)) == Some(&ant())));
+        assert!(!grid.contains_key(&(300, 0, -300)) && !grid.contains_key(&(0, 1, -1)));
+
+        assert_eq!(grid.remove(&(0, 0, 0)), Some(ant()));
+        assert_eq!(grid.remove(&(150, 0, -150)), Some(ant()));
+        assert_eq!(grid.len(), 298);
+        assert!(!grid.contains_key(&(0, 0, 0)) && !grid.contains_key(&(150, 0, -150)));
+        assert!((1..150).chain(151..300).all(|q| grid.contains_key(&(q, 0, -q))));
+    }
+
+    #[test]
+    fn test_equal_no_matter_the_order()
+    {
+        let a = Grid::from(vec![((0, 0, 0), ant()), ((1, 0, -1), ant())]);
+        let b = Grid::from(vec![((1, 0, -1), ant()), ((0, 0, 0), ant())]);
+        assert_eq!(a, b);
+    }
+}
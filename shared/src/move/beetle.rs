@@ -15,11 +15,16 @@ pub fn beetle_move(board: &Board, sq: Square) -> Vec<Square>
         neighbors(&sq).into_iter().filter(have_neighbor).collect()
     };
 
-    match board.get(&sq)
+    let mut moves: Vec<Square> = match board.get(&sq)
     {
         Some(bs) if bs.pieces.len() > 1 => neighbors(&sq).into_iter().collect(),
         _ => def(),
-    }
+    };
+
+    // No game gets a stack this high, but a stored board could have one
+    let full = |sq: &Square| matches!(board.get(sq), Some(bs) if bs.pieces.is_full());
+    moves.retain(|sq| !full(sq));
+    moves
 }
 #[cfg(test)]
 mod test
@@ -36,7 +41,7 @@ mod test
 
         let pos = [
             ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
-            (beetle_square, BoardSquare::new(beetle.clone())),
+            (beetle_square, BoardSquare::new(beetle)),
         ];
 
 
@@ -86,7 +91,7 @@ mod test
         let beetle_square = (0, 0, 0);
         let beetle = Piece::new(BoardPiece::Beetle, Color::White);
 
-        let mut vec = vec![(beetle_square, BoardSquare::new(beetle.clone()))];
+        let mut vec = vec![(beetle_square, BoardSquare::new(beetle))];
         for sq in neighbors(&beetle_square)
         {
             vec.push((sq, BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))));
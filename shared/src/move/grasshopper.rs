@@ -16,11 +16,17 @@ pub fn grasshopper_move(board: &Board, sq: Square) -> Vec<Square>
         {
             start = sq_add(start, add);
         }
-        (st != start).then(|| start)
+        if st != start
+        {
+            Some(start)
+        }
+        else
+        {
+            None
+        }
     };
 
 
-
     CUBE_DIR_VEC.into_iter().filter_map(explore_dir).collect()
 }
 
@@ -39,7 +45,7 @@ mod test
 
         let pos = [
             ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
-            (grasshopper_square, BoardSquare::new(grasshopper.clone())),
+            (grasshopper_square, BoardSquare::new(grasshopper)),
         ];
 
 
@@ -66,7 +72,7 @@ mod test
         let pos = [
             ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
             ((-1, 0, 1), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
-            (grasshopper_square, BoardSquare::new(grasshopper.clone())),
+            (grasshopper_square, BoardSquare::new(grasshopper)),
         ];
 
 
@@ -90,7 +96,7 @@ mod test
         let grasshopper_square = (0, 0, 0);
         let grasshopper = Piece::new(BoardPiece::Grasshopper, Color::White);
 
-        let mut pos = vec![(grasshopper_square, BoardSquare::new(grasshopper.clone()))];
+        let mut pos = vec![(grasshopper_square, BoardSquare::new(grasshopper))];
         for sq in crate::r#move::neighbors(&grasshopper_square)
         {
             pos.push((sq, BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))));
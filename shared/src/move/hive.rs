@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{model::*, r#move::neighbors};
+
+/// The squares whose piece can not be lifted without splitting the hive in
+/// two. These are the articulation points of the graph of occupied squares,
+/// except for stacks, which stay occupied when their top piece moves.
+pub fn pinned(board: &Board) -> HashSet<Square>
+{
+    let mut search = Articulation {
+        board,
+        visited: HashMap::with_capacity(board.len()),
+        pinned: HashSet::new(),
+    };
+
+    if let Some(root) = board.iter().map(|(sq, _)| *sq).find(|sq| occupied(board, sq))
+    {
+        search.visit(root, None, 0);
+    }
+    search.pinned
+}
+
+/// Whether moving the piece at `from` to `to` keeps the hive in one piece,
+/// given the squares `pinned` in the current position
+pub fn keeps_hive(board: &Board, pinned: &HashSet<Square>, from: Square, to: Square) -> bool
+{
+    if pinned.contains(&from)
+    {
+        return false;
+    }
+
+    // The piece has to end up touching the rest of the hive
+    let stacked = matches!(board.get(&from), Some(bs) if bs.pieces.len() > 1);
+    occupied(board, &to)
+        || neighbors(&to)
+            .into_iter()
+            .any(|sq| (sq != from || stacked) && occupied(board, &sq))
+}
+
+/// Keeps the squares in `to` that the piece at `from` can move to without
+/// breaking the hive
+pub fn one_hive(
+    board: &Board,
+    pinned: &HashSet<Square>,
+    from: Square,
+    mut to: Vec<Square>,
+) -> Vec<Square>
+{
+    to.retain(|to| keeps_hive(board, pinned, from, *to));
+    to
+}
+
+/// Whether moving the piece at `from` to `to` splits the hive
+pub fn create_island(board: &Board, from: Square, to: Square) -> bool
+{
+    !keeps_hive(board, &pinned(board), from, to)
+}
+
+fn occupied(board: &Board, sq: &Square) -> bool
+{
+    matches!(board.get(sq), Some(bs) if !bs.pieces.is_empty())
+}
+
+/// Tarjan's depth first search for articulation points
+struct Articulation<'a>
+{
+    board:   &'a Board,
+    /// `(depth, lowest depth reachable)` of every square seen so far
+    visited: HashMap<Square, (usize, usize)>,
+    pinned:  HashSet<Square>,
+}
+
+impl Articulation<'_>
+{
+    /// Visits `sq` and returns the lowest depth reachable from it
+    fn visit(&mut self, sq: Square, parent: Option<Square>, depth: usize) -> usize
+    {
+        self.visited.insert(sq, (depth, depth));
+
+        let mut low = depth;
+        let mut children = 0;
+        let mut cut = false;
+
+        for next in neighbors(&sq)
+        {
+            if !occupied(self.board, &next) || Some(next) == parent
+            {
+                continue;
+            }
+
+            match self.visited.get(&next)
+            {
+                Some((seen, _)) => low = low.min(*seen),
+                None =>
+                {
+                    children += 1;
+                    let reach = self.visit(next, Some(sq), depth + 1);
+                    low = low.min(reach);
+
+                    // Nothing below `next` reaches above `sq`
+                    cut |= parent.is_some() && reach >= depth;
+                },
+            }
+        }
+
+        let root_cut = parent.is_none() && children > 1;
+        let stacked = matches!(self.board.get(&sq), Some(bs) if bs.pieces.len() > 1);
+        if (cut || root_cut) && !stacked
+        {
+            self.pinned.insert(sq);
+        }
+
+        self.visited.insert(sq, (depth, low));
+        low
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn board(squares: &[Square]) -> Board
+    {
+        let mut board = Board::default();
+        for sq in squares
+        {
+            board.insert(*sq, BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black)));
+        }
+        board
+    }
+
+    #[test]
+    fn test_pinned_line()
+    {
+        let board = board(&[(0, -1, 1), (0, 0, 0), (0, 1, -1), (0, 2, -2)]);
+        let pinned = pinned(&board);
+
+        assert_eq!(pinned.len(), 2);
+        assert!(pinned.contains(&(0, 0, 0)) && pinned.contains(&(0, 1, -1)));
+    }
+
+    #[test]
+    fn test_pinned_piece_can_not_reconnect()
+    {
+        // Sliding the middle ant to (1, -1, 0) would leave a connected hive,
+        // but it is split while the ant is moving
+        let board = board(&[(0, -1, 1), (0, 0, 0), (1, 0, -1)]);
+        let pinned = pinned(&board);
+
+        assert!(pinned.contains(&(0, 0, 0)));
+        assert!(create_island(&board, (0, 0, 0), (1, -1, 0)));
+        assert!(!create_island(&board, (1, 0, -1), (1, -1, 0)));
+    }
+
+    #[test]
+    fn test_nothing_pinned_in_a_ring()
+    {
+        let ring = neighbors(&(0, 0, 0));
+        assert!(pinned(&board(&ring)).is_empty());
+
+        // The center holds nothing together either
+        let mut full = ring.to_vec();
+        full.push((0, 0, 0));
+        assert!(pinned(&board(&full)).is_empty());
+    }
+
+    #[test]
+    fn test_stacks_are_never_pinned()
+    {
+        let mut board = board(&[(0, -1, 1), (0, 0, 0), (0, 1, -1)]);
+        assert!(pinned(&board).contains(&(0, 0, 0)));
+
+        let beetle = Piece::new(BoardPiece::Beetle, Color::White);
+        let mut stack = BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black));
+        stack.place_piece(beetle).unwrap();
+        board.insert((0, 0, 0), stack);
+        assert!(pinned(&board).is_empty());
+
+        // The beetle can step down next to where it was
+        assert!(keeps_hive(&board, &HashSet::new(), (0, 0, 0), (1, 0, -1)));
+    }
+
+    #[test]
+    fn test_piece_has_to_touch_the_hive()
+    {
+        let board = board(&[(0, 0, 0), (0, 1, -1)]);
+        let pinned = pinned(&board);
+
+        assert!(keeps_hive(&board, &pinned, (0, 1, -1), (1, 0, -1)));
+        assert!(!keeps_hive(&board, &pinned, (0, 1, -1), (0, 2, -2)));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    model::*,
+    r#move::{neighbors, slides},
+};
+
+pub fn ladybug_move(board: &Board, sq: Square) -> Vec<Square>
+{
+    // The square the ladybug leaves is empty while it moves
+    let occupied = |s: &Square| *s != sq && board.contains_key(s);
+
+    // Each step goes over the top of the hive, which stacks can gate
+    let step = |from: &Square, to: &Square| slides(board, sq, *from, *to);
+
+    let mut res = Vec::new();
+    for first in neighbors(&sq).into_iter().filter(occupied).filter(|s| step(&sq, s))
+    {
+        for second in neighbors(&first).into_iter().filter(occupied).filter(|s| step(&first, s))
+        {
+            for third in neighbors(&second)
+            {
+                let free = third != sq && board.empty_square(&third);
+                if free && !res.contains(&third) && step(&second, &third)
+                {
+                    res.push(third);
+                }
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn test_ladybug_simple()
+    {
+        let mut board = Board::default();
+
+        let ladybug_square = (1, 0, -1);
+        let ladybug = Piece::new(BoardPiece::Ladybug, Color::White);
+
+        let pos = [
+            ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            ((-1, 0, 1), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            (ladybug_square, BoardSquare::new(ladybug)),
+        ];
+
+        board.from_iter(pos.into_iter());
+        board.turns = 3;
+
+        let mut legal_moves = ladybug_move(&board, ladybug_square);
+
+        // Up on (0, 0, 0), over to (-1, 0, 1) and down on any of its empty
+        // neighbors
+        let mut ans = vec![(-1, 1, 0), (-1, -1, 2), (-2, 0, 2), (-2, 1, 1), (0, -1, 1)];
+
+        ans.sort();
+        legal_moves.sort();
+
+        assert_eq!(legal_moves, ans);
+    }
+
+    #[test]
+    fn test_ladybug_needs_two_pieces()
+    {
+        let mut board = Board::default();
+
+        let ladybug_square = (1, 0, -1);
+        let ladybug = Piece::new(BoardPiece::Ladybug, Color::White);
+
+        let pos = [
+            ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            (ladybug_square, BoardSquare::new(ladybug)),
+        ];
+
+        board.from_iter(pos.into_iter());
+        board.turns = 3;
+
+        assert!(ladybug_move(&board, ladybug_square).is_empty());
+    }
+
+    #[test]
+    fn test_ladybug_gate_on_top()
+    {
+        let mut board = Board::default();
+
+        let ladybug_square = (1, 0, -1);
+        let ladybug = Piece::new(BoardPiece::Ladybug, Color::White);
+        let ant = || BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black));
+
+        // The gate between (0, 0, 0) and (-1, 0, 1) is one piece high, as
+        // high as the ladybug walks over it
+        let gate = [(0, -1, 1), (-1, 1, 0)];
+        let pos = [
+            ((0, 0, 0), ant()),
+            ((-1, 0, 1), ant()),
+            (gate[0], ant()),
+            (gate[1], ant()),
+            (ladybug_square, BoardSquare::new(ladybug)),
+        ];
+
+        board.from_iter(pos.into_iter());
+        board.turns = 8;
+
+        // Only reachable by way of (-1, 0, 1)
+        let far_side = (-2, 0, 2);
+        assert!(ladybug_move(&board, ladybug_square).contains(&far_side));
+
+        let beetle = Piece::new(BoardPiece::Beetle, Color::White);
+        board.place_piece(beetle, gate[0], None);
+        assert!(ladybug_move(&board, ladybug_square).contains(&far_side));
+
+        // With beetles on both sides the ladybug can not pass between them
+        board.place_piece(beetle, gate[1], None);
+        assert!(!ladybug_move(&board, ladybug_square).contains(&far_side));
+    }
+
+    #[test]
+    fn test_ladybug_one_hive()
+    {
+        let mut board = Board::default();
+
+        let ladybug_square = (0, 0, 0);
+        let ladybug = Piece::new(BoardPiece::Ladybug, Color::White);
+
+        let pos = [
+            ((1, 0, -1), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            ((2, 0, -2), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            ((-1, 0, 1), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            (ladybug_square, BoardSquare::new(ladybug)),
+        ];
+
+        board.from_iter(pos.into_iter());
+        board.turns = 8;
+
+        // The ladybug holds the hive together, so it is not allowed to move
+        assert!(!ladybug_move(&board, ladybug_square).is_empty());
+        assert!(crate::r#move::legal_moves(&ladybug, &mut board, Some(ladybug_square)).is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use crate::model::*;
 
 mod ant;
@@ -6,6 +8,9 @@ use ant::*;
 mod beetle;
 use beetle::*;
 
+mod ladybug;
+use ladybug::*;
+
 mod spider;
 use spider::*;
 
@@ -15,9 +20,45 @@ use queen::*;
 mod grasshopper;
 use grasshopper::*;
 
+mod mosquito;
+use mosquito::*;
+
+mod pillbug;
+use pillbug::*;
+pub use pillbug::{has_pillbug_ability, pillbug_throws, thrown_by};
+
+mod hive;
+use hive::one_hive;
+pub use hive::{create_island, keeps_hive, pinned};
+
 pub fn legal_moves(p: &Piece, board: &mut Board, board_pos: Option<Square>) -> Vec<Square>
 {
-    if board.is_complete()
+    let pinned = match board_pos
+    {
+        Some(_) => pinned(board),
+        None => Default::default(),
+    };
+    legal_moves_pinned(p, board, board_pos, &pinned)
+}
+
+/// `legal_moves` with the squares `pinned` by the one hive rule worked out
+/// already, so they are only found once per position
+fn legal_moves_pinned(
+    p: &Piece,
+    board: &mut Board,
+    board_pos: Option<Square>,
+    pinned: &HashSet<Square>,
+) -> Vec<Square>
+{
+    if board.is_complete() || (board_pos.is_none() && board.in_reserve(p) == 0)
+    {
+        return Vec::new();
+    }
+
+    // With the tournament rule the queen can not be either player's first piece
+    let first_placement = board.turns < 2;
+    let is_queen = p.r#type == BoardPiece::Queen;
+    if board.options.tournament && first_placement && is_queen
     {
         return Vec::new();
     }
@@ -44,7 +85,11 @@ pub fn legal_moves(p: &Piece, board: &mut Board, board_pos: Option<Square>) -> V
 
             match board_pos
             {
-                Some(pos) => legal_on_board_move(p, board, pos),
+                // Nothing moves before its queen is placed, and the piece
+                // moved last turn has to stay put
+                Some(_) if no_queen_placed => Vec::new(),
+                Some(pos) if board.last_moved == Some(pos) => Vec::new(),
+                Some(pos) => legal_on_board_move(p, board, pos, pinned),
                 None => legal_new_piece_moves(p, board),
             }
         },
@@ -52,13 +97,108 @@ pub fn legal_moves(p: &Piece, board: &mut Board, board_pos: Option<Square>) -> V
 }
 
 
+/// Every move `color` can play in the current position: placements from the
+/// reserve, moves of the pieces on top and pillbug throws, each of them once.
+/// An empty result means the player has to pass.
+pub fn generate_moves(board: &mut Board, color: Color) -> Vec<Move>
+{
+    let mut moves = Vec::new();
+    let mut seen = HashSet::new();
+    let pinned = pinned(board);
+
+    let mut add = |r#move: Move| {
+        if seen.insert((r#move.piece, r#move.old_sq, r#move.sq, r#move.kind))
+        {
+            moves.push(r#move);
+        }
+    };
+
+    for r#type in BoardPiece::ALL
+    {
+        let piece = Piece::new(r#type, color);
+        if board.in_reserve(&piece) > 0
+        {
+            let squares = legal_moves(&piece, board, None);
+            let piece = board.identify(piece, None);
+            squares.into_iter().for_each(|sq| add(Move::new(piece, sq, None)));
+        }
+    }
+
+    let on_board = board
+        .iter()
+        .filter(|(_, bs)| bs.top().color == color)
+        .map(|(sq, bs)| (*sq, *bs.top()))
+        .collect::<Vec<_>>();
+
+    for (from, piece) in on_board
+    {
+        let squares = legal_moves_pinned(&piece, board, Some(from), &pinned);
+        squares.into_iter().for_each(|sq| add(Move::new(piece, sq, Some(from))));
+
+        for (thrown, to) in pillbug_throws_pinned(board, from, &pinned)
+        {
+            let piece = *board.get(&thrown).unwrap().top();
+            add(Move {
+                kind: MoveKind::Pillbug(from),
+                ..Move::new(piece, to, Some(thrown))
+            });
+        }
+    }
+
+    moves
+}
+
+
+/// Whether moving the piece at `from` to `to` is a regular move or has to be
+/// made by one of the pillbugs of the player to move
+pub fn move_kind(board: &mut Board, piece: &Piece, from: Square, to: Square) -> MoveKind
+{
+    if piece.color == board.to_move() && legal_moves(piece, board, Some(from)).contains(&to)
+    {
+        return MoveKind::Regular;
+    }
+
+    thrown_by(board, from)
+        .into_iter()
+        .find(|(_, sq)| *sq == to)
+        .map(|(pillbug, _)| MoveKind::Pillbug(pillbug))
+        .unwrap_or_default()
+}
+
+
 pub fn square_has_neighbors(sq: Square, board: &Board, me: Square) -> bool
 {
     neighbors(&sq).into_iter().filter(|s| *s != me).any(|s| board.contains_key(&s))
 }
 
+/// Whether a piece on top of `from` can move over to the neighboring `to`,
+/// landing on top of whatever is there. It can not squeeze through a gate,
+/// where the stacks on both sides of its path are higher than `from` and `to`.
+/// `lifted` is the square the piece was picked up from, one piece lower now.
+pub fn slides(board: &Board, lifted: Square, from: Square, to: Square) -> bool
+{
+    let height = |sq: &Square| match board.get(sq)
+    {
+        Some(bs) if *sq == lifted => bs.pieces.len() - 1,
+        Some(bs) => bs.pieces.len(),
+        None => 0,
+    };
 
-fn legal_on_board_move(p: &Piece, board: &mut Board, sq: Square) -> Vec<Square>
+    let level = height(&from).max(height(&to));
+    let from_neighbors = neighbors(&from);
+    neighbors(&to)
+        .into_iter()
+        .filter(|sq| from_neighbors.contains(sq))
+        .any(|gate| height(&gate) <= level)
+}
+
+
+fn legal_on_board_move(
+    p: &Piece,
+    board: &mut Board,
+    sq: Square,
+    pinned: &HashSet<Square>,
+) -> Vec<Square>
 {
     let vec = match p.r#type
     {
@@ -67,8 +207,11 @@ fn legal_on_board_move(p: &Piece, board: &mut Board, sq: Square) -> Vec<Square>
         BoardPiece::Grasshopper => grasshopper_move(board, sq),
         BoardPiece::Queen => queen_move(board, sq),
         BoardPiece::Spider => spider_move(board, sq),
+        BoardPiece::Mosquito => mosquito_move(board, sq),
+        BoardPiece::Ladybug => ladybug_move(board, sq),
+        BoardPiece::Pillbug => pillbug_move(board, sq),
     };
-    create_island_multiple(board, sq, vec)
+    one_hive(board, pinned, sq, vec)
 }
 
 // Hmm, t-this can be simplified r-right?
@@ -89,14 +232,8 @@ fn legal_new_piece_moves(piece: &Piece, board: &Board) -> Vec<Square>
 
     board
         .iter()
-        .filter_map(|(sq, bp)| {
-            (bp.top().color == piece.color).then(|| {
-                neighbors(sq)
-                    .into_iter()
-                    .filter_map(|sq| not_touching_other_color(sq).then(|| sq))
-            })
-        })
-        .flatten()
+        .filter(|(_, bp)| bp.top().color == piece.color)
+        .flat_map(|(sq, _)| neighbors(sq).into_iter().filter(|sq| not_touching_other_color(*sq)))
         .collect()
 }
 
@@ -118,140 +255,6 @@ pub fn neighbors(sq: &Square) -> [Square; 6]
 }
 
 
-fn create_island_multiple(board: &Board, from: Square, mut vec: Vec<Square>) -> Vec<Square>
-{
-    let mut global = Vec::with_capacity(board.len());
-    let mut local = Vec::with_capacity(board.len());
-
-    vec.retain(|to| !_create_island(board, from, *to, &mut global, &mut local));
-    vec
-}
-
-pub fn _create_island(
-    board: &Board,
-    from: Square,
-    to: Square,
-    global: &mut Vec<Square>,
-    local: &mut Vec<Square>,
-) -> bool
-{
-    let mut board = board.clone();
-    board.play_from_to(from, to);
-
-    let mut iter = neighbors(&from)
-        .into_iter()
-        .filter(|sq| match board.get(sq)
-        {
-            Some(bs) => !bs.pieces.is_empty(),
-            _ => false,
-        })
-        .chain(std::iter::once(to));
-
-    let res = if let Some(fst) = iter.next()
-    {
-        //if global.is_empty()
-        {
-            global.clear();
-            create_set(&board, fst, global);
-        }
-
-        iter.any(|s| {
-            local.clear();
-            check_global(&board, s, &global, local)
-        })
-    }
-    else
-    {
-        false
-    };
-
-    //board.un_play_from_to(from, to);
-    res
-}
-
-
-// @TODO, make this better
-fn create_set(board: &Board, fst: Square, set: &mut Vec<Square>)
-{
-    for sq in neighbors(&fst).into_iter().filter(|sq| match board.get(sq)
-    {
-        Some(bs) => !bs.pieces.is_empty(),
-        _ => false,
-    })
-    {
-        if !set.contains(&sq)
-        //if set.insert(sq)
-        {
-            set.push(sq);
-            create_set(board, sq, set);
-        }
-    }
-}
-
-fn check_global(board: &Board, sq: Square, global: &Vec<Square>, local: &mut Vec<Square>) -> bool
-{
-    if !global.contains(&sq)
-    {
-        return true;
-    }
-
-    //for sq in neighbors(&sq).into_iter().filter(|s| board.board.contains_key(s))
-    for sq in neighbors(&sq).into_iter().filter(|sq| match board.get(sq)
-    {
-        Some(bs) => !bs.pieces.is_empty(),
-        _ => false,
-    })
-    {
-        if !local.contains(&sq)
-        //if local.push(sq)
-        {
-            local.push(sq);
-            if check_global(board, sq, global, local)
-            {
-                return true;
-            }
-        }
-    }
-    false
-}
-
-
-pub fn create_island(board: &mut Board, from: Square, to: Square) -> bool
-{
-    let mut board = board.clone();
-    board.play_from_to(from, to);
-
-    let mut iter = neighbors(&from)
-        .into_iter()
-        .filter(|sq| match board.get(sq)
-        {
-            Some(bs) => !bs.pieces.is_empty(),
-            _ => false,
-        })
-        .chain(std::iter::once(to));
-
-    let res = if let Some(fst) = iter.next()
-    {
-        let mut global = Vec::with_capacity(board.len());
-        let mut local = Vec::with_capacity(board.len());
-
-
-        create_set(&board, fst, &mut global);
-
-        iter.any(|s| {
-            local.clear();
-            check_global(&board, s, &global, &mut local)
-        })
-    }
-    else
-    {
-        false
-    };
-
-    //board.un_play_from_to(from, to);
-    res
-}
-
 #[cfg(test)]
 mod test
 {
@@ -293,12 +296,12 @@ mod test
         let from = (0, 1, -1);
         let to = (1, 0, -1);
 
-        assert!(!create_island(&mut board, from, to));
+        assert!(!create_island(&board, from, to));
 
         let from = (0, 1, -1);
         let to = (0, 2, -2);
 
-        assert!(create_island(&mut board, from, to));
+        assert!(create_island(&board, from, to));
     }
 
     #[test]
@@ -332,12 +335,12 @@ mod test
         let from = (1, 0, -1);
         let to = (2, -1, -1);
 
-        assert!(!create_island(&mut board, from, to));
+        assert!(!create_island(&board, from, to));
 
         let from = (1, 0, -1);
         let to = (3, -1, -2);
 
-        assert!(create_island(&mut board, from, to));
+        assert!(create_island(&board, from, to));
     }
 
     #[test]
@@ -375,17 +378,93 @@ mod test
         let from = (0, 0, 0);
         let to = (1, 0, -1);
 
-        assert!(!create_island(&mut board, from, to));
+        assert!(!create_island(&board, from, to));
 
         let from = (-1, -2, 3);
         let to = (-2, -1, 3);
 
-        assert!(!create_island(&mut board, from, to));
+        assert!(!create_island(&board, from, to));
 
         board.remove((2, 1, -3));
         let from = (-1, -2, 3);
         let to = (-2, -1, 3);
 
-        assert!(create_island(&mut board, from, to));
+        assert!(create_island(&board, from, to));
+    }
+
+    #[test]
+    fn test_tournament_rule_forbids_queen_first()
+    {
+        let queen = |color| Piece::new(BoardPiece::Queen, color);
+        let ant = |color| Piece::new(BoardPiece::Ant, color);
+
+        let mut board = Board::new(GameOptions {
+            tournament: true,
+            ..Default::default()
+        });
+
+        assert!(legal_moves(&queen(Color::White), &mut board, None).is_empty());
+        assert_eq!(legal_moves(&ant(Color::White), &mut board, None), vec![(0, 0, 0)]);
+
+        board.place_piece(ant(Color::White), (0, 0, 0), None);
+        assert!(legal_moves(&queen(Color::Black), &mut board, None).is_empty());
+        assert_eq!(legal_moves(&ant(Color::Black), &mut board, None).len(), 6);
+
+        board.place_piece(ant(Color::Black), (1, -1, 0), None);
+        assert!(!legal_moves(&queen(Color::White), &mut board, None).is_empty());
+
+        // Without the rule the queen can open the game
+        let mut board = Board::default();
+        assert_eq!(legal_moves(&queen(Color::White), &mut board, None), vec![(0, 0, 0)]);
+    }
+
+    #[test]
+    fn test_nothing_moves_before_the_queen()
+    {
+        let ant = |color| Piece::new(BoardPiece::Ant, color);
+        let mut board = Board::default();
+        board.place_piece(ant(Color::White), (0, 0, 0), None);
+        board.place_piece(ant(Color::Black), (1, -1, 0), None);
+        board.place_piece(ant(Color::White), (-1, 1, 0), None);
+        board.place_piece(ant(Color::Black), (2, -2, 0), None);
+
+        // The ant could go around the hive, but only new pieces can be placed
+        assert!(legal_moves(&ant(Color::White), &mut board, Some((-1, 1, 0))).is_empty());
+        assert!(generate_moves(&mut board, Color::White).iter().all(|m| m.old_sq.is_none()));
+
+        board.place_piece(Piece::new(BoardPiece::Queen, Color::White), (-1, 0, 1), None);
+        board.place_piece(ant(Color::Black), (3, -3, 0), None);
+        assert!(!legal_moves(&ant(Color::White), &mut board, Some((-1, 1, 0))).is_empty());
+
+        // Black has not placed its queen yet
+        board.place_piece(ant(Color::White), (-2, 1, 1), None);
+        assert!(legal_moves(&ant(Color::Black), &mut board, Some((3, -3, 0))).is_empty());
+    }
+
+    #[test]
+    fn test_generate_moves()
+    {
+        let mut board = Board::default();
+        let ant = |color| Piece::new(BoardPiece::Ant, color);
+
+        // Five kinds of pieces, one square
+        assert_eq!(generate_moves(&mut board, Color::White).len(), 5);
+
+        board.place_piece(ant(Color::White), (0, 0, 0), None);
+        board.place_piece(ant(Color::Black), (1, -1, 0), None);
+        board.place_piece(ant(Color::White), (-1, 0, 1), None);
+        board.place_piece(ant(Color::Black), (2, -1, -1), None);
+
+        // Squares next to both white ants are only counted once
+        let moves = generate_moves(&mut board, Color::White);
+        let placements = moves.iter().filter(|m| m.old_sq.is_none()).count();
+        assert_eq!(placements, 5 * 5);
+        assert!(moves.iter().all(|m| m.piece.ordinal > 0));
+
+        for (i, r#move) in moves.iter().enumerate()
+        {
+            assert!(!moves[i + 1..].contains(r#move));
+            assert!(board.clone().play_move(r#move.clone()).is_ok());
+        }
     }
 }
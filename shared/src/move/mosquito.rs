@@ -0,0 +1,156 @@
+use crate::{
+    model::*,
+    r#move::{
+        ant_move, beetle_move, grasshopper_move, ladybug_move, neighbors, pillbug_move, queen_move,
+        spider_move,
+    },
+};
+
+pub fn mosquito_move(board: &Board, sq: Square) -> Vec<Square>
+{
+    // On top of the hive the mosquito moves like a beetle
+    if matches!(board.get(&sq), Some(bs) if bs.pieces.len() > 1)
+    {
+        return beetle_move(board, sq);
+    }
+
+    let mut types = Vec::with_capacity(6);
+    for bs in neighbors(&sq).into_iter().filter_map(|sq| board.get(&sq))
+    {
+        let r#type = bs.top().r#type;
+        if r#type != BoardPiece::Mosquito && !types.contains(&r#type)
+        {
+            types.push(r#type);
+        }
+    }
+
+    let mut res = Vec::new();
+    for r#type in types
+    {
+        let moves = match r#type
+        {
+            BoardPiece::Ant => ant_move(board, sq),
+            BoardPiece::Beetle => beetle_move(board, sq),
+            BoardPiece::Grasshopper => grasshopper_move(board, sq),
+            BoardPiece::Queen => queen_move(board, sq),
+            BoardPiece::Spider => spider_move(board, sq),
+            BoardPiece::Ladybug => ladybug_move(board, sq),
+            BoardPiece::Pillbug => pillbug_move(board, sq),
+            BoardPiece::Mosquito => unreachable!(),
+        };
+
+        for sq in moves
+        {
+            if !res.contains(&sq)
+            {
+                res.push(sq);
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn test_mosquito_copies_grasshopper()
+    {
+        let mut board = Board::default();
+
+        let mosquito_square = (1, 0, -1);
+        let mosquito = Piece::new(BoardPiece::Mosquito, Color::White);
+
+        let pos = [
+            ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Grasshopper, Color::Black))),
+            (mosquito_square, BoardSquare::new(mosquito)),
+        ];
+
+        board.from_iter(pos.into_iter());
+        board.turns = 3;
+
+        let mut legal_moves = mosquito_move(&board, mosquito_square);
+        let mut ans = grasshopper_move(&board, mosquito_square);
+
+        ans.sort();
+        legal_moves.sort();
+
+        assert_eq!(legal_moves, ans);
+        assert_eq!(legal_moves, vec![(-1, 0, 1)]);
+    }
+
+    #[test]
+    fn test_mosquito_combines_neighbors()
+    {
+        let mut board = Board::default();
+
+        let mosquito_square = (1, 0, -1);
+        let mosquito = Piece::new(BoardPiece::Mosquito, Color::White);
+
+        let pos = [
+            ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Queen, Color::Black))),
+            ((1, -1, 0), BoardSquare::new(Piece::new(BoardPiece::Beetle, Color::Black))),
+            (mosquito_square, BoardSquare::new(mosquito)),
+        ];
+
+        board.from_iter(pos.into_iter());
+        board.turns = 8;
+
+        let mut legal_moves = mosquito_move(&board, mosquito_square);
+        let mut ans = beetle_move(&board, mosquito_square);
+        for sq in queen_move(&board, mosquito_square)
+        {
+            if !ans.contains(&sq)
+            {
+                ans.push(sq);
+            }
+        }
+
+        ans.sort();
+        legal_moves.sort();
+
+        assert_eq!(legal_moves, ans);
+        assert!(legal_moves.contains(&(0, 0, 0)));
+    }
+
+    #[test]
+    fn test_mosquito_next_to_mosquito()
+    {
+        let mut board = Board::default();
+
+        let mosquito_square = (1, 0, -1);
+        let mosquito = Piece::new(BoardPiece::Mosquito, Color::White);
+
+        let pos = [
+            ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Mosquito, Color::Black))),
+            (mosquito_square, BoardSquare::new(mosquito)),
+        ];
+
+        board.from_iter(pos.into_iter());
+        board.turns = 8;
+
+        assert!(mosquito_move(&board, mosquito_square).is_empty());
+    }
+
+    #[test]
+    fn test_mosquito_on_top()
+    {
+        let mut board = Board::default();
+
+        let mosquito_square = (0, 0, 0);
+        let mosquito = Piece::new(BoardPiece::Mosquito, Color::White);
+
+        board.place_piece(Piece::new(BoardPiece::Ant, Color::Black), mosquito_square, None);
+        board.place_piece(mosquito, mosquito_square, None);
+
+        let mut legal_moves = mosquito_move(&board, mosquito_square);
+        let mut ans = neighbors(&mosquito_square);
+
+        ans.sort();
+        legal_moves.sort();
+
+        assert_eq!(legal_moves, ans);
+    }
+}
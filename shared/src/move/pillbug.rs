@@ -0,0 +1,305 @@
+use std::collections::HashSet;
+
+use crate::{
+    model::*,
+    r#move::{neighbors, one_hive, pinned, queen_move, slides},
+};
+
+pub fn pillbug_move(board: &Board, sq: Square) -> Vec<Square>
+{
+    queen_move(board, sq)
+}
+
+/// Whether the piece at `sq` can use the pillbug's special ability, that is an
+/// unstacked pillbug or an unstacked mosquito touching a pillbug
+pub fn has_pillbug_ability(board: &Board, sq: Square) -> bool
+{
+    let is_pillbug = |sq: &Square| match board.get(sq)
+    {
+        Some(bs) => bs.top().r#type == BoardPiece::Pillbug,
+        None => false,
+    };
+
+    match board.get(&sq)
+    {
+        Some(bs) if bs.pieces.len() == 1 => match bs.top().r#type
+        {
+            BoardPiece::Pillbug => true,
+            BoardPiece::Mosquito => neighbors(&sq).iter().any(is_pillbug),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Every `(from, to)` the pillbug at `sq` can move an adjacent piece with its
+/// special ability: pick up an unstacked neighbor and drop it on an empty
+/// square next to the pillbug
+pub fn pillbug_throws(board: &Board, sq: Square) -> Vec<(Square, Square)>
+{
+    pillbug_throws_pinned(board, sq, &pinned(board))
+}
+
+/// `pillbug_throws` with the squares `pinned` by the one hive rule worked out
+/// already, so they are only found once per position
+pub(crate) fn pillbug_throws_pinned(
+    board: &Board,
+    sq: Square,
+    pinned: &HashSet<Square>,
+) -> Vec<(Square, Square)>
+{
+    if board.is_complete() || board.last_moved == Some(sq) || !has_pillbug_ability(board, sq)
+    {
+        return Vec::new();
+    }
+
+    // Like moving, the ability can only be used once the queen is placed. So
+    // on the turn the queen has to be placed, placing it is all there is
+    let color = board.get(&sq).unwrap().top().color;
+    if board.queens[color as usize].is_none()
+    {
+        return Vec::new();
+    }
+
+    let empty = neighbors(&sq)
+        .into_iter()
+        .filter(|sq| board.empty_square(sq))
+        .collect::<Vec<_>>();
+
+    let mut res = Vec::new();
+    for from in neighbors(&sq)
+    {
+        let unstacked = matches!(board.get(&from), Some(bs) if bs.pieces.len() == 1);
+
+        // The piece goes up on the pillbug and down again, and stacks on
+        // either side can gate both
+        if unstacked && board.last_moved != Some(from) && slides(board, from, from, sq)
+        {
+            let down = empty.iter().copied().filter(|to| slides(board, from, sq, *to)).collect();
+            let to = one_hive(board, pinned, from, down);
+            res.extend(to.into_iter().map(|to| (from, to)));
+        }
+    }
+    res
+}
+
+/// Every `(pillbug, to)` the side to move can use to move the piece at `from`
+pub fn thrown_by(board: &Board, from: Square) -> Vec<(Square, Square)>
+{
+    let color = board.to_move();
+    let pinned = pinned(board);
+
+    let mut res = Vec::new();
+    for pillbug in neighbors(&from)
+    {
+        if matches!(board.get(&pillbug), Some(bs) if bs.top().color == color)
+        {
+            let throws = pillbug_throws_pinned(board, pillbug, &pinned);
+            res.extend(throws.into_iter().filter(|(f, _)| *f == from).map(|(_, to)| (pillbug, to)));
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn test_pillbug_move_simple()
+    {
+        let mut board = Board::default();
+
+        let pillbug_square = (1, 0, -1);
+        let pillbug = Piece::new(BoardPiece::Pillbug, Color::White);
+
+        let pos = [
+            ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            (pillbug_square, BoardSquare::new(pillbug)),
+        ];
+
+        board.from_iter(pos.into_iter());
+        board.turns = 3;
+
+        let mut legal_moves = pillbug_move(&board, pillbug_square);
+        let mut ans = vec![(1, -1, 0), (0, 1, -1)];
+
+        ans.sort();
+        legal_moves.sort();
+
+        assert_eq!(legal_moves, ans);
+    }
+
+    #[test]
+    fn test_pillbug_throws()
+    {
+        let mut board = Board::default();
+
+        let pillbug_square = (0, 0, 0);
+        let pillbug = Piece::new(BoardPiece::Pillbug, Color::White);
+
+        let pos = [
+            ((1, 0, -1), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            ((-1, 0, 1), BoardSquare::new(Piece::new(BoardPiece::Queen, Color::White))),
+            (pillbug_square, BoardSquare::new(pillbug)),
+        ];
+
+        board.from_iter(pos.into_iter());
+        board.queens[Color::White as usize] = Some((-1, 0, 1));
+        board.turns = 8;
+
+        let mut throws = pillbug_throws(&board, pillbug_square);
+        let mut ans = vec![];
+        for from in [(1, 0, -1), (-1, 0, 1)]
+        {
+            for to in [(1, -1, 0), (0, -1, 1), (-1, 1, 0), (0, 1, -1)]
+            {
+                ans.push((from, to));
+            }
+        }
+
+        ans.sort();
+        throws.sort();
+
+        assert_eq!(throws, ans);
+    }
+
+    #[test]
+    fn test_pillbug_throw_restrictions()
+    {
+        let mut board = Board::default();
+
+        let pillbug_square = (0, 0, 0);
+        let pillbug = Piece::new(BoardPiece::Pillbug, Color::White);
+
+        let pos = [
+            // Holds (2, 0, -2) to the hive
+            ((1, 0, -1), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            ((2, 0, -2), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            // Just moved here
+            ((-1, 0, 1), BoardSquare::new(Piece::new(BoardPiece::Queen, Color::Black))),
+            ((3, 0, -3), BoardSquare::new(Piece::new(BoardPiece::Queen, Color::White))),
+            (pillbug_square, BoardSquare::new(pillbug)),
+        ];
+
+        board.from_iter(pos.into_iter());
+        board.queens = [Some((3, 0, -3)), Some((-1, 0, 1))];
+        board.last_moved = Some((-1, 0, 1));
+
+        // A stacked piece can not be moved either
+        board.place_piece(Piece::new(BoardPiece::Ant, Color::White), (0, 1, -1), None);
+        board.place_piece(Piece::new(BoardPiece::Beetle, Color::Black), (0, 1, -1), None);
+        board.last_moved = Some((-1, 0, 1));
+
+        assert!(pillbug_throws(&board, pillbug_square).is_empty());
+    }
+
+    #[test]
+    fn test_pillbug_throws_follow_the_game()
+    {
+        let mut board = Board::default();
+
+        let pillbug_square = (0, 0, 0);
+        let queen_square = (-1, 0, 1);
+        let ant = || BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black));
+
+        let pos = [
+            ((1, 0, -1), ant()),
+            (pillbug_square, BoardSquare::new(Piece::new(BoardPiece::Pillbug, Color::White))),
+        ];
+        board.from_iter(pos.into_iter());
+        board.turns = 6;
+
+        // White has to place the queen this turn
+        assert!(pillbug_throws(&board, pillbug_square).is_empty());
+
+        board.place_piece(Piece::new(BoardPiece::Queen, Color::White), queen_square, None);
+        board.turns = 8;
+        assert!(!pillbug_throws(&board, pillbug_square).is_empty());
+
+        // Nothing moves once the queen is surrounded
+        for sq in neighbors(&queen_square)
+        {
+            if !board.contains_key(&sq)
+            {
+                board.insert(sq, ant());
+            }
+        }
+        assert!(board.is_complete());
+        assert!(pillbug_throws(&board, pillbug_square).is_empty());
+    }
+
+    #[test]
+    fn test_pillbug_throw_gates()
+    {
+        let mut board = Board::default();
+
+        let pillbug_square = (0, 0, 0);
+        let queen_square = (-1, 0, 1);
+        let thrown = (1, 0, -1);
+        let gate = [(1, -1, 0), (0, 1, -1)];
+        let ant = || BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black));
+
+        let pos = [
+            (thrown, ant()),
+            (gate[0], ant()),
+            (gate[1], ant()),
+            (queen_square, BoardSquare::new(Piece::new(BoardPiece::Queen, Color::White))),
+            (pillbug_square, BoardSquare::new(Piece::new(BoardPiece::Pillbug, Color::White))),
+        ];
+        board.from_iter(pos.into_iter());
+        board.queens[Color::White as usize] = Some(queen_square);
+
+        let beetle = Piece::new(BoardPiece::Beetle, Color::Black);
+        let throws_of = |board: &mut Board| {
+            board.last_moved = None;
+            let mut to = pillbug_throws(board, pillbug_square)
+                .into_iter()
+                .filter(|(from, _)| *from == thrown)
+                .map(|(_, to)| to)
+                .collect::<Vec<_>>();
+            to.sort();
+            to
+        };
+
+        // One stack is no gate
+        board.place_piece(beetle, gate[0], None);
+        assert_eq!(throws_of(&mut board), vec![(-1, 1, 0), (0, -1, 1)]);
+
+        // Off the pillbug, between the queen and the first stack
+        board.place_piece(Piece::new(BoardPiece::Beetle, Color::White), queen_square, None);
+        assert_eq!(throws_of(&mut board), vec![(-1, 1, 0)]);
+
+        // And up on it, between the two stacks
+        board.place_piece(beetle, gate[1], None);
+        assert!(throws_of(&mut board).is_empty());
+    }
+
+    #[test]
+    fn test_mosquito_next_to_pillbug()
+    {
+        let mut board = Board::default();
+
+        let mosquito_square = (0, 0, 0);
+        let mosquito = Piece::new(BoardPiece::Mosquito, Color::White);
+
+        let pos = [
+            ((1, 0, -1), BoardSquare::new(Piece::new(BoardPiece::Pillbug, Color::Black))),
+            ((-1, 0, 1), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
+            (mosquito_square, BoardSquare::new(mosquito)),
+        ];
+
+        board.from_iter(pos.into_iter());
+
+        assert!(has_pillbug_ability(&board, mosquito_square));
+        assert!(pillbug_throws(&board, mosquito_square).is_empty());
+
+        // Not until the queen is placed
+        board.place_piece(Piece::new(BoardPiece::Queen, Color::White), (-2, 0, 2), None);
+        assert!(!pillbug_throws(&board, mosquito_square).is_empty());
+
+        board.place_piece(Piece::new(BoardPiece::Beetle, Color::Black), (1, 0, -1), None);
+        assert!(!has_pillbug_ability(&board, mosquito_square));
+    }
+}
@@ -29,7 +29,7 @@ mod test
 
         let pos = [
             ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
-            (queen_square, BoardSquare::new(queen.clone())),
+            (queen_square, BoardSquare::new(queen)),
         ];
 
 
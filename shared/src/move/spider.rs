@@ -75,7 +75,7 @@ mod test
 
         let pos = [
             ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
-            (spider_square, BoardSquare::new(spider.clone())),
+            (spider_square, BoardSquare::new(spider)),
         ];
 
 
@@ -104,7 +104,7 @@ mod test
         let pos = [
             ((0, 0, 0), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
             ((1, 0, -1), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
-            (spider_square, BoardSquare::new(spider.clone())),
+            (spider_square, BoardSquare::new(spider)),
         ];
 
 
@@ -135,7 +135,7 @@ mod test
             ((1, 1, -2), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
             ((-1, 0, 1), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
             ((-2, 1, 1), BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black))),
-            (spider_square, BoardSquare::new(spider.clone())),
+            (spider_square, BoardSquare::new(spider)),
         ];
 
 
@@ -0,0 +1,350 @@
+//! Moves written the way Boardspace and the Universal Hive Protocol write
+//! them, e.g. `wA1 -bS2` or `bB1 wQ`.
+//!
+//! The second part places the piece next to a reference piece. A symbol in
+//! front of the reference puts it to the left of it, a symbol after it to the
+//! right of it. No symbol means on top of the reference.
+
+use crate::{model::*, r#move::move_kind};
+
+/// `(offset from the reference, symbol in front, symbol after)`
+const DIRECTIONS: [(Square, &str, &str); 6] = [
+    ((1, 0, -1), "", "-"),
+    ((1, -1, 0), "", "/"),
+    ((0, 1, -1), "", "\\"),
+    ((-1, 0, 1), "-", ""),
+    ((-1, 1, 0), "/", ""),
+    ((0, -1, 1), "\\", ""),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotationError
+{
+    Empty,
+    /// Not a piece, e.g. `wX1`
+    InvalidPiece(String),
+    /// Not a position next to a piece, e.g. `-wA1-`
+    InvalidPosition(String),
+    /// A piece there are several of without a number, e.g. `wA`
+    Ambiguous(String),
+    /// The reference piece has not been placed
+    NotOnBoard(String),
+    /// The piece is neither on the board nor the next one in the reserve
+    NotInReserve(String),
+    /// Every move but the first needs a position
+    MissingPosition,
+    /// Not a game type, e.g. `Base+X`
+    InvalidGameType(String),
+}
+
+/// Reads the expansions of a game type like `Base+MLP`
+pub fn parse_game_type(text: &str) -> Result<GameOptions, NotationError>
+{
+    let invalid = || NotationError::InvalidGameType(text.into());
+
+    let expansions = match text.strip_prefix("Base")
+    {
+        Some("") => "",
+        Some(rest) => rest.strip_prefix('+').filter(|e| !e.is_empty()).ok_or_else(invalid)?,
+        None => return Err(invalid()),
+    };
+
+    let mut options = GameOptions::default();
+    for letter in expansions.chars()
+    {
+        match BoardPiece::from_letter(letter)
+        {
+            Some(BoardPiece::Mosquito) if !options.mosquito => options.mosquito = true,
+            Some(BoardPiece::Ladybug) if !options.ladybug => options.ladybug = true,
+            Some(BoardPiece::Pillbug) if !options.pillbug => options.pillbug = true,
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(options)
+}
+
+/// Writes the expansions in `options` as a game type like `Base+MLP`
+pub fn format_game_type(options: &GameOptions) -> String
+{
+    let expansions = options
+        .pieces()
+        .filter(BoardPiece::is_expansion)
+        .map(|piece| piece.letter())
+        .collect::<String>();
+
+    match expansions.is_empty()
+    {
+        true => "Base".into(),
+        false => format!("Base+{expansions}"),
+    }
+}
+
+/// Reads a move for the player to move in the current position. The move is
+/// not checked against the rules, use `Board::play_move` for that.
+pub fn parse_move(board: &mut Board, text: &str) -> Result<Move, NotationError>
+{
+    let mut parts = text.split_whitespace();
+    let piece = parts.next().ok_or(NotationError::Empty)?;
+
+    if piece.eq_ignore_ascii_case("pass")
+    {
+        return Ok(Move {
+            kind: MoveKind::Pass,
+            ..Move::new(Piece::new(BoardPiece::Queen, board.to_move()), (0, 0, 0), None)
+        });
+    }
+
+    let piece = parse_piece(piece)?;
+
+    let sq = match parts.next()
+    {
+        Some(position) => parse_position(board, position)?,
+        None if board.is_empty() => (0, 0, 0),
+        None => return Err(NotationError::MissingPosition),
+    };
+
+    if let Some(rest) = parts.next()
+    {
+        return Err(NotationError::InvalidPosition(rest.into()));
+    }
+
+    match find_piece(board, &piece)
+    {
+        Some(from) =>
+        {
+            let kind = move_kind(board, &piece, from, sq);
+            Ok(Move {
+                kind,
+                ..Move::new(piece, sq, Some(from))
+            })
+        },
+        None if board.identify(piece, None) == piece => Ok(Move::new(piece, sq, None)),
+        None => Err(NotationError::NotInReserve(piece.to_string())),
+    }
+}
+
+/// Writes `move`, which is about to be played on `board`, relative to the
+/// pieces already on the board
+pub fn format_move(board: &Board, r#move: &Move) -> String
+{
+    if r#move.kind == MoveKind::Pass
+    {
+        return "pass".into();
+    }
+
+    let piece = board.identify(r#move.piece, r#move.old_sq);
+
+    // The piece the moving piece leaves behind, if any
+    let reference = |sq: Square| match board.get(&sq)
+    {
+        Some(bs) if Some(sq) == r#move.old_sq => bs.pieces.iter().rev().nth(1),
+        Some(bs) => Some(bs.top()),
+        None => None,
+    };
+
+    if let Some(below) = reference(r#move.sq)
+    {
+        return format!("{piece} {below}");
+    }
+
+    DIRECTIONS
+        .iter()
+        .find_map(|((q, r, s), before, after)| {
+            let (sq_q, sq_r, sq_s) = r#move.sq;
+            let next_to = reference((sq_q - q, sq_r - r, sq_s - s))?;
+            Some(format!("{piece} {before}{next_to}{after}"))
+        })
+        .unwrap_or_else(|| piece.to_string())
+}
+
+fn parse_piece(text: &str) -> Result<Piece, NotationError>
+{
+    let invalid = || NotationError::InvalidPiece(text.into());
+    let mut chars = text.chars();
+
+    let color = match chars.next()
+    {
+        Some('w') => Color::White,
+        Some('b') => Color::Black,
+        _ => return Err(invalid()),
+    };
+    let r#type = chars.next().and_then(BoardPiece::from_letter).ok_or_else(invalid)?;
+
+    let ordinal = match chars.as_str()
+    {
+        "" if r#type.count() == 1 => 1,
+        "" => return Err(NotationError::Ambiguous(text.into())),
+        n => n
+            .parse::<u8>()
+            .ok()
+            .filter(|n| (1..=r#type.count() as u8).contains(n))
+            .ok_or_else(invalid)?,
+    };
+
+    Ok(Piece {
+        ordinal,
+        ..Piece::new(r#type, color)
+    })
+}
+
+fn parse_position(board: &Board, text: &str) -> Result<Square, NotationError>
+{
+    let invalid = || NotationError::InvalidPosition(text.into());
+    let is_symbol = |c: char| matches!(c, '-' | '/' | '\\');
+
+    let reference = text.trim_matches(is_symbol);
+    let before = &text[..text.find(reference).ok_or_else(invalid)?];
+    let after = &text[before.len() + reference.len()..];
+
+    let offset = match (before, after)
+    {
+        ("", "") => (0, 0, 0),
+        _ => DIRECTIONS
+            .iter()
+            .find(|(_, b, a)| *b == before && *a == after)
+            .map(|(offset, _, _)| *offset)
+            .ok_or_else(invalid)?,
+    };
+
+    let reference = parse_piece(reference)?;
+    let (q, r, s) = find_piece(board, &reference)
+        .ok_or_else(|| NotationError::NotOnBoard(reference.to_string()))?;
+
+    Ok((q + offset.0, r + offset.1, s + offset.2))
+}
+
+/// The square `piece` is on, even when it is covered by other pieces
+fn find_piece(board: &Board, piece: &Piece) -> Option<Square>
+{
+    board
+        .iter()
+        .find(|(_, bs)| bs.pieces.iter().any(|p| p == piece))
+        .map(|(sq, _)| *sq)
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_round_trip()
+    {
+        let mut board = Board::default();
+
+        let game =
+            ["wS1", "bS1 wS1-", "wQ -wS1", "bQ bS1/", "wG1 /wQ", "bB1 bQ\\", "wG1 wQ/", "bB1 bQ"];
+
+        for text in game
+        {
+            let r#move = parse_move(&mut board, text).unwrap();
+
+            // There are several ways to write most moves, they all have to
+            // read back as the same move
+            let written = format_move(&board, &r#move);
+            assert_eq!(parse_move(&mut board, &written), Ok(r#move.clone()));
+
+            assert!(board.play_move(r#move).is_ok());
+        }
+
+        assert_eq!(board.get(&(0, -1, 1)).unwrap().top().to_string(), "wG1");
+        assert_eq!(board.get(&(2, -1, -1)).unwrap().pieces.len(), 2);
+    }
+
+    #[test]
+    fn test_format_move()
+    {
+        let mut board = Board::default();
+        let ant = Piece::new(BoardPiece::Ant, Color::White);
+        let beetle = Piece::new(BoardPiece::Beetle, Color::Black);
+
+        assert_eq!(format_move(&board, &Move::new(ant, (0, 0, 0), None)), "wA1");
+        board.place_piece(ant, (0, 0, 0), None);
+
+        assert_eq!(format_move(&board, &Move::new(beetle, (1, 0, -1), None)), "bB1 wA1-");
+        assert_eq!(format_move(&board, &Move::new(beetle, (0, -1, 1), None)), "bB1 \\wA1");
+        board.place_piece(beetle, (1, 0, -1), None);
+
+        // Climbing on top of a piece, and back down from it
+        let climb = Move::new(beetle, (0, 0, 0), Some((1, 0, -1)));
+        assert_eq!(format_move(&board, &climb), "bB1 wA1");
+        board.place_piece(beetle, (0, 0, 0), Some((1, 0, -1)));
+
+        let down = Move::new(beetle, (1, -1, 0), Some((0, 0, 0)));
+        assert_eq!(format_move(&board, &down), "bB1 wA1/");
+    }
+
+    #[test]
+    fn test_game_type()
+    {
+        assert_eq!(parse_game_type("Base"), Ok(GameOptions::default()));
+        assert_eq!(format_game_type(&GameOptions::default()), "Base");
+
+        let options = parse_game_type("Base+PM").unwrap();
+        assert!(options.mosquito && options.pillbug && !options.ladybug);
+        assert_eq!(format_game_type(&options), "Base+MP");
+
+        for text in ["", "Base+", "Base+X", "Base+MM", "Extended"]
+        {
+            assert_eq!(parse_game_type(text), Err(NotationError::InvalidGameType(text.into())));
+        }
+    }
+
+    #[test]
+    fn test_parse_pass()
+    {
+        let mut board = Board::default();
+        board.turns = 5;
+
+        let pass = parse_move(&mut board, "pass").unwrap();
+        assert_eq!(pass.kind, MoveKind::Pass);
+        assert_eq!(pass.piece.color, Color::Black);
+        assert_eq!(format_move(&board, &pass), "pass");
+    }
+
+    #[test]
+    fn test_parse_errors()
+    {
+        let mut board = Board::default();
+        assert_eq!(parse_move(&mut board, ""), Err(NotationError::Empty));
+        assert_eq!(parse_move(&mut board, "wX1"), Err(NotationError::InvalidPiece("wX1".into())));
+        assert_eq!(parse_move(&mut board, "wA4"), Err(NotationError::InvalidPiece("wA4".into())));
+        assert_eq!(parse_move(&mut board, "wA"), Err(NotationError::Ambiguous("wA".into())));
+        assert_eq!(parse_move(&mut board, "wA2"), Err(NotationError::NotInReserve("wA2".into())));
+
+        let first = parse_move(&mut board, "wA1").unwrap();
+        board.play_move(first).unwrap();
+
+        assert_eq!(parse_move(&mut board, "bA1"), Err(NotationError::MissingPosition));
+        assert_eq!(
+            parse_move(&mut board, "bA1 wS1-"),
+            Err(NotationError::NotOnBoard("wS1".into()))
+        );
+        assert_eq!(
+            parse_move(&mut board, "bA1 -wA1-"),
+            Err(NotationError::InvalidPosition("-wA1-".into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_pillbug_throw()
+    {
+        let mut board = Board::new(GameOptions {
+            pillbug: true,
+            ..Default::default()
+        });
+
+        for text in ["wP", "bQ wP-", "wQ -wP", "bA1 bQ-", "wQ \\wP", "bA1 wP\\"]
+        {
+            let r#move = parse_move(&mut board, text).unwrap();
+            board.play_move(r#move).unwrap();
+        }
+
+        // The black queen is picked up by the white pillbug
+        let throw = parse_move(&mut board, "bQ wQ-").unwrap();
+        assert_eq!(throw.kind, MoveKind::Pillbug((0, 0, 0)));
+        assert_eq!(throw.old_sq, Some((1, 0, -1)));
+        assert_eq!(format_move(&board, &throw), "bQ wQ-");
+        assert!(board.play_move(throw).is_ok());
+    }
+}
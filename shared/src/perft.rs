@@ -0,0 +1,164 @@
+//! Counts the positions reachable from a position, to check the move
+//! generation against other engines and against itself after changes.
+
+use crate::{model::*, r#move::generate_moves};
+
+/// The number of move sequences `depth` moves long from `board`. A player
+/// without moves passes, a finished game has no moves at all.
+pub fn perft(board: &mut Board, depth: usize) -> u64
+{
+    if depth == 0
+    {
+        return 1;
+    }
+    if board.is_complete()
+    {
+        return 0;
+    }
+
+    let moves = moves(board);
+    if depth == 1
+    {
+        return moves.len() as u64;
+    }
+
+    let mut count = 0;
+    for r#move in moves
+    {
+        let undo = board.make_move(r#move);
+        count += perft(board, depth - 1);
+        board.undo(undo);
+    }
+    count
+}
+
+/// `perft` for every move in the position, to find which one goes wrong
+pub fn divide(board: &mut Board, depth: usize) -> Vec<(Move, u64)>
+{
+    if board.is_complete()
+    {
+        return Vec::new();
+    }
+
+    let mut res = Vec::new();
+    for r#move in moves(board)
+    {
+        let undo = board.make_move(r#move.clone());
+        let count = perft(board, depth.saturating_sub(1));
+        board.undo(undo);
+        res.push((r#move, count));
+    }
+    res
+}
+
+/// The moves of the player to move, or a pass when they have none
+fn moves(board: &mut Board) -> Vec<Move>
+{
+    let color = board.to_move();
+    let mut moves = generate_moves(board, color);
+    if moves.is_empty()
+    {
+        moves.push(Move::pass(Default::default(), Default::default(), color));
+    }
+    moves
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::game_string::parse_game;
+
+    fn tournament(options: GameOptions) -> Board
+    {
+        Board::new(GameOptions {
+            tournament: true,
+            ..options
+        })
+    }
+
+    #[test]
+    fn test_perft_base()
+    {
+        // The counts of other engines, which all play with the tournament rule
+        let mut board = tournament(GameOptions::default());
+        for (depth, expected) in [1, 4, 96, 1440, 21600].into_iter().enumerate()
+        {
+            assert_eq!(perft(&mut board, depth), expected, "depth {depth}");
+        }
+        assert_eq!(board, tournament(GameOptions::default()));
+    }
+
+    #[test]
+    #[ignore = "slow, run with `cargo test --release -- --ignored`"]
+    fn test_perft_base_deep()
+    {
+        let mut board = tournament(GameOptions::default());
+        assert_eq!(perft(&mut board, 5), 516240);
+    }
+
+    #[test]
+    fn test_perft_expansions()
+    {
+        let options = GameOptions {
+            mosquito: true,
+            ladybug: true,
+            pillbug: true,
+            ..Default::default()
+        };
+
+        let mut board = tournament(options);
+        for (depth, expected) in [1, 7, 294, 6678, 151686].into_iter().enumerate()
+        {
+            assert_eq!(perft(&mut board, depth), expected, "depth {depth}");
+        }
+    }
+
+    #[test]
+    fn test_perft_midgame()
+    {
+        // Both queens out, a beetle on top of another and pillbugs, mosquitoes
+        // and ants next to each other. The counts are this engine's own, to
+        // notice when they change.
+        let (mut board, _) = parse_game(
+            "Base+MLP;InProgress;Black[9];wS1;bM wS1/;wM wS1\\;bG1 \\bM;wP /wM;bS1 -bG1;wQ \
+             -wS1;bQ bG1-;wB1 -wP;bP \\bS1;wB2 /wB1;bS2 -bP;wS2 wB2\\;bB1 bP/;wS2 -wB1;bA1 \
+             bM-;wB2 wB1",
+        )
+        .unwrap();
+
+        for (depth, expected) in [1, 94, 4112].into_iter().enumerate()
+        {
+            assert_eq!(perft(&mut board, depth), expected, "depth {depth}");
+        }
+    }
+
+    #[test]
+    fn test_divide_adds_up()
+    {
+        let mut board = tournament(GameOptions::default());
+        let divided = divide(&mut board, 3);
+
+        assert_eq!(divided.len(), 4);
+        assert_eq!(divided.iter().map(|(_, count)| count).sum::<u64>(), 1440);
+    }
+
+    #[test]
+    fn test_divide_passes()
+    {
+        // The white queen holds the two black ants together and every empty
+        // square next to it touches a black piece
+        let mut board = Board::default();
+        board.place_piece(Piece::new(BoardPiece::Queen, Color::White), (0, 0, 0), None);
+        for sq in [(1, -1, 0), (-1, 1, 0)]
+        {
+            board.insert(sq, BoardSquare::new(Piece::new(BoardPiece::Ant, Color::Black)));
+        }
+        board.turns = 4;
+
+        let divided = divide(&mut board, 2);
+        assert_eq!(divided.len(), 1);
+        assert_eq!(divided[0].0.kind, MoveKind::Pass);
+        assert_eq!(divided[0].1, perft(&mut board, 2));
+    }
+}
@@ -0,0 +1,171 @@
+//! Plays random games with every combination of options and checks the rules
+//! hold after every move. Each game is seeded, so a failing one can be played
+//! again with `play_game`.
+
+use std::collections::HashSet;
+
+use shared::{engine::Rng, model::*, r#move::*};
+
+/// Random moves rarely surround a queen, so games are cut off after this many
+const MAX_PLIES: usize = 200;
+
+fn options(seed: u64) -> GameOptions
+{
+    GameOptions {
+        mosquito: seed & 1 != 0,
+        ladybug: seed & 2 != 0,
+        pillbug: seed & 4 != 0,
+        tournament: seed & 8 != 0,
+        ..Default::default()
+    }
+}
+
+/// Plays a game of random moves and checks the board after each of them
+fn play_game(seed: u64)
+{
+    let mut rng = Rng::new(seed);
+    let mut board = Board::new(options(seed));
+
+    for ply in 0..MAX_PLIES
+    {
+        if board.is_complete()
+        {
+            break;
+        }
+
+        let color = board.to_move();
+        let moves = generate_moves(&mut board, color);
+        assert!(
+            lands_on_empty(&board, &moves),
+            "seed {seed} ply {ply}: a piece moves onto another\n{board:?}"
+        );
+
+        let r#move = match moves.len()
+        {
+            0 => Move::pass(Default::default(), Default::default(), color),
+            n => moves[rng.below(n)].clone(),
+        };
+
+        let before = board.clone();
+        let undo = board
+            .play_move(r#move.clone())
+            .unwrap_or_else(|e| panic!("seed {seed} ply {ply}: {move:?} was generated but {e:?}"));
+
+        let check = |board: &Board, what: &str, ok: bool| {
+            assert!(ok, "seed {seed} ply {ply}: {what} after {move:?}\n{board:?}");
+        };
+        check(&board, "the hive is split", connected(&board));
+        check(&board, "too many pieces", within_reserve(&board));
+        check(&board, "the queens are lost", queens_found(&board));
+        check(&board, "a stack has more than beetles on top", stacks_of_climbers(&board));
+
+        // Taking the move back has to give the same board again, so keep the
+        // one that was played
+        board.undo(undo.clone());
+        check(
+            &board,
+            "undo changed the board",
+            board == before && board.zobrist() == before.zobrist(),
+        );
+        board.make_move(undo.r#move);
+    }
+}
+
+/// Every occupied square can be reached from any other
+fn connected(board: &Board) -> bool
+{
+    let squares = board.iter().map(|(sq, _)| *sq).collect::<HashSet<_>>();
+    let Some(start) = squares.iter().next()
+    else
+    {
+        return true;
+    };
+
+    let mut seen = HashSet::from([*start]);
+    let mut todo = vec![*start];
+    while let Some(sq) = todo.pop()
+    {
+        for next in neighbors(&sq)
+        {
+            if squares.contains(&next) && seen.insert(next)
+            {
+                todo.push(next);
+            }
+        }
+    }
+    seen.len() == squares.len()
+}
+
+/// No more of a piece is on the board than a player has, each with its own
+/// ordinal
+fn within_reserve(board: &Board) -> bool
+{
+    let pieces = board.values().flat_map(|bs| bs.pieces.iter()).collect::<Vec<_>>();
+    let unique = pieces.iter().collect::<HashSet<_>>();
+
+    unique.len() == pieces.len()
+        && pieces.iter().all(|piece| {
+            board.options.enabled(piece.r#type)
+                && (1..=board.options.count(piece.r#type)).contains(&(piece.ordinal as usize))
+        })
+}
+
+/// `Board::queens` is where the queens are, or `None` for a queen in the
+/// reserve
+fn queens_found(board: &Board) -> bool
+{
+    [Color::White, Color::Black].into_iter().all(|color| {
+        let queen = board.iter().find(|(_, bs)| {
+            bs.pieces
+                .iter()
+                .any(|piece| piece.r#type == BoardPiece::Queen && piece.color == color)
+        });
+        board.queens[color as usize] == queen.map(|(sq, _)| *sq)
+    })
+}
+
+/// Only beetles, and mosquitoes moving like them, are ever on top of another
+/// piece
+fn stacks_of_climbers(board: &Board) -> bool
+{
+    board.values().all(|bs| {
+        bs.pieces
+            .iter()
+            .skip(1)
+            .all(|piece| matches!(piece.r#type, BoardPiece::Beetle | BoardPiece::Mosquito))
+    })
+}
+
+/// Pieces from the reserve, pieces that can not climb and thrown pieces only
+/// ever go to empty squares
+fn lands_on_empty(board: &Board, moves: &[Move]) -> bool
+{
+    moves.iter().all(|r#move| {
+        let climbs = matches!(r#move.piece.r#type, BoardPiece::Beetle | BoardPiece::Mosquito);
+        let placed = r#move.old_sq.is_none();
+        let thrown = matches!(r#move.kind, MoveKind::Pillbug(_));
+
+        (climbs && !placed && !thrown) || board.empty_square(&r#move.sq)
+    })
+}
+
+/// How many games to play: by default every combination of options once. Set
+/// `RANDOM_GAMES` for a longer run, e.g.
+/// `RANDOM_GAMES=2000 cargo test --release --test random_games`
+fn games() -> u64
+{
+    match std::env::var("RANDOM_GAMES")
+    {
+        Ok(n) => n.parse().expect("RANDOM_GAMES is a number of games"),
+        Err(_) => 16,
+    }
+}
+
+#[test]
+fn test_random_games()
+{
+    for seed in 0..games()
+    {
+        play_game(seed);
+    }
+}